@@ -117,8 +117,12 @@ mod tests {
 
     #[tokio::test]
     async fn test_convert() -> Resultat<()> {
-        // 因为绕过peer直接调用算法接口, 所以不必填写会话配置.
-        let chan = SvarogChannel::new_session(&SessionConfig::default(), SESMAN_URL, false)
+        // 因为绕过peer直接调用算法接口, 所以只须填写玩家与门限, 以便领取令牌.
+        // 玩家"1","2","3"的序号恰好是1,2,3.
+        let mut cfg = SessionConfig::default();
+        cfg.players = (1..=3).map(|i| (i.to_string(), true)).collect();
+        cfg.threshold = 1;
+        let sid = SvarogChannel::new_session(&cfg, SESMAN_URL, false)
             .await
             .catch_()?;
 
@@ -145,7 +149,10 @@ mod tests {
 
         let mut sign_threads = Vec::new();
         for i in signers.iter() {
-            let chan = chan.clone();
+            let token = sid.tokens.get(&i.to_string()).ifnone_()?;
+            let (chan, _) = SvarogChannel::use_session(&sid.value, token, SESMAN_URL, false)
+                .await
+                .catch_()?;
             let keystore = keystores.get(i).ifnone_()?.clone();
             let signers = signers.clone();
             let (hmsg, dpath) = task.clone();
@@ -1,2 +1,4 @@
 mod svarog;
 pub use svarog::*;
+pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("descriptor.bin");
+pub mod serde_base64;
@@ -31,9 +31,22 @@ fn main() {
             }
         }
         fs::create_dir_all(&rust_dir).unwrap();
+        // For gRPC server reflection.
+        lib_rs.push(
+            "pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(\"descriptor.bin\");"
+                .to_string(),
+        );
+        // Hand-written, for the payloads in JSON.
+        lib_rs.push("pub mod serde_base64;".to_string());
         tonic_build::configure()
             .out_dir(&rust_dir)
+            .file_descriptor_set_path(format!("{}/descriptor.bin", rust_dir))
             .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+            .message_attribute(".", "#[serde(default)]")
+            .field_attribute(
+                ".svarog.Message.obj",
+                "#[serde(with = \"crate::serde_base64\")]",
+            )
             .compile(&protos, &[&proto_dir])
             .unwrap();
     }
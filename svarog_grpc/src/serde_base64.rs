@@ -0,0 +1,17 @@
+//! Serde of `optional bytes` fields as base64 strings, so that their JSON stays compact.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, ser: S) -> Result<S::Ok, S::Error> {
+    match bytes {
+        Some(bytes) => ser.serialize_some(&STANDARD.encode(bytes)),
+        None => ser.serialize_none(),
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Vec<u8>>, D::Error> {
+    let text: Option<String> = Option::deserialize(de)?;
+    text.map(|text| STANDARD.decode(text).map_err(D::Error::custom))
+        .transpose()
+}
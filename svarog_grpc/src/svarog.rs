@@ -1,5 +1,6 @@
 // This file is @generated by prost-build.
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SessionConfig {
@@ -13,15 +14,95 @@ pub struct SessionConfig {
     pub players: ::std::collections::HashMap<::prost::alloc::string::String, bool>,
     #[prost(map = "string, bool", tag = "6")]
     pub players_reshared: ::std::collections::HashMap<::prost::alloc::string::String, bool>,
+    /// Lifetime of the session in seconds, counted from its creation.
+    /// 0 means the default lifetime of sesman.
+    #[prost(uint64, tag = "7")]
+    pub ttl: u64,
+    /// Remaining lifetime of the session in seconds. Filled by sesman.
+    #[prost(uint64, tag = "8")]
+    pub ttl_remaining: u64,
+    /// Client certificate of each player, keyed by the player name.
+    /// If not empty, sesman must run with mTLS, and accepts messages
+    /// from a player only over a connection authenticated by its certificate.
+    /// The empty name stands for the same party as in `SessionId.tokens`.
+    #[prost(map = "string, message", tag = "9")]
+    pub player_certs: ::std::collections::HashMap<::prost::alloc::string::String, CertBinding>,
+    /// Encrypt point-to-point messages end to end, so that sesman only sees
+    /// ciphertext. Every player posts an X25519 public key, derived from its
+    /// identity key, to the session under topic "__e2e_key/<player name>" when
+    /// it joins. Needs `player_keys`, which authenticate these public keys.
+    #[prost(bool, tag = "10")]
+    pub e2e: bool,
+    /// Ed25519 identity public key of each player in lowercase hex, keyed by the
+    /// player name. If not empty, every message is signed by its sender over
+    /// (session_id, topic, src, dst, seq, obj), and receivers reject the messages
+    /// not signed by a party that may send as `src`.
+    #[prost(map = "string, string", tag = "11")]
+    pub player_keys:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::string::String>,
+    /// After each round of broadcasts, every player that received them posts a digest
+    /// of each broadcast it sent or received in the round under topic
+    /// "__echo/<topic>/<player name>" with the seq of the round, and aborts if another
+    /// player received a different payload. Pair with `player_keys`, otherwise
+    /// sesman could forge the echoes as well.
+    #[prost(bool, tag = "12")]
+    pub echo_broadcast: bool,
+    /// What the session is for. Sesman rejects configs unfit for the kind in
+    /// NewSession, and so do the players before joining. UNSPECIFIED only gets
+    /// the checks common to all kinds.
+    #[prost(enumeration = "SessionKind", tag = "13")]
+    pub kind: i32,
 }
+/// Fill either field. If both are filled, both should match.
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CertBinding {
+    /// Subject of the certificate in the order of its RDNs,
+    /// e.g. "C=CN, O=Taiyi, CN=Alice".
+    #[prost(string, tag = "1")]
+    pub subject: ::prost::alloc::string::String,
+    /// Lowercase hex of the SHA-256 digest of the DER-encoded SubjectPublicKeyInfo.
+    #[prost(string, tag = "2")]
+    pub spki_sha256: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SessionId {
     #[prost(string, tag = "1")]
     pub value: ::prost::alloc::string::String,
+    /// Bearer token of each player, returned by NewSession.
+    /// Players send it as gRPC metadata `authorization: Bearer <token>`.
+    /// The empty name stands for a party outside of the player list,
+    /// e.g. the mnemonic provider of KeygenMnem.
+    #[prost(map = "string, string", tag = "2")]
+    pub tokens:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JoinReply {
+    /// Name of the player that the token stands for.
+    #[prost(string, tag = "1")]
+    pub player: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AbortRequest {
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Message {
@@ -36,9 +117,205 @@ pub struct Message {
     #[prost(uint64, tag = "5")]
     pub seq: u64,
     #[prost(bytes = "vec", optional, tag = "6")]
+    #[serde(with = "crate::serde_base64")]
     pub obj: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExchangeReply {
+    /// The requested message, or the posted one without `obj` as the acknowledgement.
+    #[prost(message, optional, tag = "1")]
+    pub msg: ::core::option::Option<Message>,
+    /// The gRPC status code and message if the post or request failed, 0 otherwise.
+    #[prost(int32, tag = "2")]
+    pub code: i32,
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Equivocation {
+    #[prost(string, tag = "1")]
+    pub topic: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub src: u64,
+    #[prost(uint64, tag = "3")]
+    pub dst: u64,
+    #[prost(uint64, tag = "4")]
+    pub seq: u64,
+    /// Unix time in milliseconds.
+    #[prost(uint64, tag = "5")]
+    pub detected_at: u64,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VecEquivocation {
+    #[prost(message, repeated, tag = "1")]
+    pub values: ::prost::alloc::vec::Vec<Equivocation>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PostedMessage {
+    #[prost(string, tag = "1")]
+    pub topic: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub src: u64,
+    #[prost(uint64, tag = "3")]
+    pub dst: u64,
+    #[prost(uint64, tag = "4")]
+    pub seq: u64,
+    /// Bytes of `Message.obj`.
+    #[prost(uint64, tag = "5")]
+    pub size: u64,
+    /// Unix time in milliseconds.
+    #[prost(uint64, tag = "6")]
+    pub arrived_at: u64,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WaitingMessage {
+    #[prost(string, tag = "1")]
+    pub topic: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub src: u64,
+    #[prost(uint64, tag = "3")]
+    pub dst: u64,
+    #[prost(uint64, tag = "4")]
+    pub seq: u64,
+    /// Name of the player who is waiting.
+    #[prost(string, tag = "5")]
+    pub waiter: ::prost::alloc::string::String,
+    /// Remote address of the connection of the waiter.
+    #[prost(string, tag = "6")]
+    pub conn: ::prost::alloc::string::String,
+    /// Unix time in milliseconds.
+    #[prost(uint64, tag = "7")]
+    pub since: u64,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionStatus {
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub posted: ::prost::alloc::vec::Vec<PostedMessage>,
+    /// Only the messages not posted yet.
+    #[prost(message, repeated, tag = "3")]
+    pub waiting: ::prost::alloc::vec::Vec<WaitingMessage>,
+    #[prost(enumeration = "SessionState", tag = "4")]
+    pub state: i32,
+    /// Unix times in milliseconds, keyed by player name. A player joins at its
+    /// first request of the session other than the reports, `JoinSession` or not.
+    #[prost(map = "string, uint64", tag = "5")]
+    pub joined: ::std::collections::HashMap<::prost::alloc::string::String, u64>,
+    #[prost(map = "string, uint64", tag = "6")]
+    pub completed: ::std::collections::HashMap<::prost::alloc::string::String, u64>,
+    /// Unix times in milliseconds. 0 if not yet.
+    #[prost(uint64, tag = "7")]
+    pub created_at: u64,
+    #[prost(uint64, tag = "8")]
+    pub finished_at: u64,
+}
+/// 0 means unlimited.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Limits {
+    /// Bytes of `Message.obj`.
+    #[prost(uint64, tag = "1")]
+    pub max_payload: u64,
+    /// Messages in one `Inbox` or `Outbox` call.
+    #[prost(uint64, tag = "2")]
+    pub max_batch: u64,
+    #[prost(uint64, tag = "3")]
+    pub max_msgs_per_session: u64,
+    #[prost(uint64, tag = "4")]
+    pub max_bytes_per_session: u64,
+    /// Sessions not expired yet.
+    #[prost(uint64, tag = "5")]
+    pub max_sessions: u64,
+    /// `Outbox` calls and `Exchange` requests waiting at the same time,
+    /// over the same connection.
+    #[prost(uint64, tag = "6")]
+    pub max_waiters_per_conn: u64,
+    /// Bytes of all the stored messages.
+    #[prost(uint64, tag = "7")]
+    pub memory_budget: u64,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionSummary {
+    #[prost(message, optional, tag = "1")]
+    pub config: ::core::option::Option<SessionConfig>,
+    #[prost(enumeration = "SessionState", tag = "2")]
+    pub state: i32,
+    /// Unix times in milliseconds.
+    #[prost(uint64, tag = "3")]
+    pub created_at: u64,
+    #[prost(uint64, tag = "4")]
+    pub expire_at: u64,
+    #[prost(uint64, tag = "5")]
+    pub age_ms: u64,
+    /// Messages posted to the session, and the bytes of their payloads.
+    #[prost(uint64, tag = "6")]
+    pub messages: u64,
+    #[prost(uint64, tag = "7")]
+    pub bytes: u64,
+    /// Players who have joined or completed the session,
+    /// out of the attending players.
+    #[prost(uint64, tag = "8")]
+    pub joined: u64,
+    #[prost(uint64, tag = "9")]
+    pub completed: u64,
+    #[prost(uint64, tag = "10")]
+    pub participants: u64,
+    /// Messages being waited for but not posted yet.
+    #[prost(uint64, tag = "11")]
+    pub waiting: u64,
+    #[prost(uint64, tag = "12")]
+    pub equivocations: u64,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VecSessionSummary {
+    #[prost(message, repeated, tag = "1")]
+    pub values: ::prost::alloc::vec::Vec<SessionSummary>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Usage {
+    #[prost(uint64, tag = "1")]
+    pub sessions: u64,
+    #[prost(uint64, tag = "2")]
+    pub messages: u64,
+    #[prost(uint64, tag = "3")]
+    pub bytes: u64,
+    #[prost(uint64, tag = "4")]
+    pub waiters: u64,
+    #[prost(message, optional, tag = "5")]
+    pub limits: ::core::option::Option<Limits>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VecMessage {
@@ -46,6 +323,7 @@ pub struct VecMessage {
     pub values: ::prost::alloc::vec::Vec<Message>,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct EchoMessage {
@@ -53,9 +331,111 @@ pub struct EchoMessage {
     pub value: ::prost::alloc::string::String,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Void {}
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    ::prost::Enumeration,
+)]
+#[repr(i32)]
+pub enum SessionKind {
+    Unspecified = 0,
+    /// Also KeygenMnemi. Every player attends, 0 < threshold < players.
+    Keygen = 1,
+    /// Same as KEYGEN.
+    KeygenMnem = 2,
+    /// Any attending players, threshold is not used.
+    Sign = 3,
+    /// The attending players provide, every player in players_reshared attends,
+    /// 0 < threshold < players_reshared.
+    Reshare = 4,
+}
+impl SessionKind {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SessionKind::Unspecified => "UNSPECIFIED",
+            SessionKind::Keygen => "KEYGEN",
+            SessionKind::KeygenMnem => "KEYGEN_MNEM",
+            SessionKind::Sign => "SIGN",
+            SessionKind::Reshare => "RESHARE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "UNSPECIFIED" => Some(Self::Unspecified),
+            "KEYGEN" => Some(Self::Keygen),
+            "KEYGEN_MNEM" => Some(Self::KeygenMnem),
+            "SIGN" => Some(Self::Sign),
+            "RESHARE" => Some(Self::Reshare),
+            _ => None,
+        }
+    }
+}
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    ::prost::Enumeration,
+)]
+#[repr(i32)]
+pub enum SessionState {
+    Created = 0,
+    /// Some player has joined.
+    Running = 1,
+    /// Every player has completed.
+    Completed = 2,
+    Aborted = 3,
+    Expired = 4,
+}
+impl SessionState {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SessionState::Created => "CREATED",
+            SessionState::Running => "RUNNING",
+            SessionState::Completed => "COMPLETED",
+            SessionState::Aborted => "ABORTED",
+            SessionState::Expired => "EXPIRED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CREATED" => Some(Self::Created),
+            "RUNNING" => Some(Self::Running),
+            "COMPLETED" => Some(Self::Completed),
+            "ABORTED" => Some(Self::Aborted),
+            "EXPIRED" => Some(Self::Expired),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod mpc_session_manager_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -177,89 +557,1000 @@ pub mod mpc_session_manager_client {
             ));
             self.inner.unary(req, path, codec).await
         }
-        pub async fn inbox(
-            &mut self,
-            request: impl tonic::IntoRequest<super::VecMessage>,
-        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::new(
-                    tonic::Code::Unknown,
-                    format!("Service was not ready: {}", e.into()),
-                )
-            })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Inbox");
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Inbox"));
-            self.inner.unary(req, path, codec).await
+        pub async fn inbox(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VecMessage>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Inbox");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Inbox"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn outbox(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VecMessage>,
+        ) -> std::result::Result<tonic::Response<super::VecMessage>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Outbox");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Outbox"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Push messages with `obj` to post them, and messages without `obj` to
+        /// request them. Each post is acknowledged once stored, and requested
+        /// messages are streamed back as they arrive. A failed post or request is
+        /// answered with its error, and the stream goes on.
+        pub async fn exchange(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::Message>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ExchangeReply>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Exchange");
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Exchange"));
+            self.inner.streaming(req, path, codec).await
+        }
+        /// Players join the session before exchanging messages, and complete it
+        /// after they are done. The session completes once every player completes.
+        pub async fn join_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::JoinReply>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/JoinSession");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.MpcSessionManager", "JoinSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn complete_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/CompleteSession");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "svarog.MpcSessionManager",
+                "CompleteSession",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn abort_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AbortRequest>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/AbortSession");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.MpcSessionManager", "AbortSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Messages are write-once. Different payloads sent to the same
+        /// (session_id, topic, src, dst, seq) are recorded as equivocations.
+        pub async fn get_equivocations(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::VecEquivocation>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/GetEquivocations");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "svarog.MpcSessionManager",
+                "GetEquivocations",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Messages posted so far, and messages being waited for.
+        pub async fn get_session_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::SessionStatus>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/GetSessionStatus");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "svarog.MpcSessionManager",
+                "GetSessionStatus",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn ping(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Void>,
+        ) -> std::result::Result<tonic::Response<super::EchoMessage>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Ping");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Ping"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated client implementations.
+pub mod sesman_admin_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::http::Uri;
+    use tonic::codegen::*;
+    /// Operational RPCs for the operators of sesman, not for the players.
+    /// Every call carries `authorization: Bearer <admin token>`.
+    #[derive(Debug, Clone)]
+    pub struct SesmanAdminClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl SesmanAdminClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> SesmanAdminClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> SesmanAdminClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
+                Into<StdError> + Send + Sync,
+        {
+            SesmanAdminClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Sessions not expired yet, in no particular order.
+        pub async fn list_sessions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Void>,
+        ) -> std::result::Result<tonic::Response<super::VecSessionSummary>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.SesmanAdmin/ListSessions");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.SesmanAdmin", "ListSessions"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn inspect_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::SessionSummary>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.SesmanAdmin/InspectSession");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.SesmanAdmin", "InspectSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Remove the session and its messages at once.
+        /// Players waiting in the session fail with `NotFound`.
+        pub async fn purge_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.SesmanAdmin/PurgeSession");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.SesmanAdmin", "PurgeSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Current resource usage of sesman, and its limits.
+        pub async fn get_usage(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Void>,
+        ) -> std::result::Result<tonic::Response<super::Usage>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/svarog.SesmanAdmin/GetUsage");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("svarog.SesmanAdmin", "GetUsage"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod mpc_session_manager_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with MpcSessionManagerServer.
+    #[async_trait]
+    pub trait MpcSessionManager: Send + Sync + 'static {
+        async fn new_session(
+            &self,
+            request: tonic::Request<super::SessionConfig>,
+        ) -> std::result::Result<tonic::Response<super::SessionId>, tonic::Status>;
+        async fn get_session_config(
+            &self,
+            request: tonic::Request<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::SessionConfig>, tonic::Status>;
+        async fn inbox(
+            &self,
+            request: tonic::Request<super::VecMessage>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status>;
+        async fn outbox(
+            &self,
+            request: tonic::Request<super::VecMessage>,
+        ) -> std::result::Result<tonic::Response<super::VecMessage>, tonic::Status>;
+        /// Server streaming response type for the Exchange method.
+        type ExchangeStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ExchangeReply, tonic::Status>,
+            > + Send
+            + 'static;
+        /// Push messages with `obj` to post them, and messages without `obj` to
+        /// request them. Each post is acknowledged once stored, and requested
+        /// messages are streamed back as they arrive. A failed post or request is
+        /// answered with its error, and the stream goes on.
+        async fn exchange(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::Message>>,
+        ) -> std::result::Result<tonic::Response<Self::ExchangeStream>, tonic::Status>;
+        /// Players join the session before exchanging messages, and complete it
+        /// after they are done. The session completes once every player completes.
+        async fn join_session(
+            &self,
+            request: tonic::Request<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::JoinReply>, tonic::Status>;
+        async fn complete_session(
+            &self,
+            request: tonic::Request<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status>;
+        async fn abort_session(
+            &self,
+            request: tonic::Request<super::AbortRequest>,
+        ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status>;
+        /// Messages are write-once. Different payloads sent to the same
+        /// (session_id, topic, src, dst, seq) are recorded as equivocations.
+        async fn get_equivocations(
+            &self,
+            request: tonic::Request<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::VecEquivocation>, tonic::Status>;
+        /// Messages posted so far, and messages being waited for.
+        async fn get_session_status(
+            &self,
+            request: tonic::Request<super::SessionId>,
+        ) -> std::result::Result<tonic::Response<super::SessionStatus>, tonic::Status>;
+        async fn ping(
+            &self,
+            request: tonic::Request<super::Void>,
+        ) -> std::result::Result<tonic::Response<super::EchoMessage>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct MpcSessionManagerServer<T: MpcSessionManager> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: MpcSessionManager> MpcSessionManagerServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for MpcSessionManagerServer<T>
+    where
+        T: MpcSessionManager,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/svarog.MpcSessionManager/NewSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct NewSessionSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionConfig> for NewSessionSvc<T> {
+                        type Response = super::SessionId;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionConfig>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::new_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = NewSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/GetSessionConfig" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetSessionConfigSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionId>
+                        for GetSessionConfigSvc<T>
+                    {
+                        type Response = super::SessionConfig;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionId>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::get_session_config(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetSessionConfigSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/Inbox" => {
+                    #[allow(non_camel_case_types)]
+                    struct InboxSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::VecMessage> for InboxSvc<T> {
+                        type Response = super::Void;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VecMessage>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::inbox(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = InboxSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/Outbox" => {
+                    #[allow(non_camel_case_types)]
+                    struct OutboxSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::VecMessage> for OutboxSvc<T> {
+                        type Response = super::VecMessage;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VecMessage>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::outbox(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = OutboxSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/Exchange" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExchangeSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::StreamingService<super::Message> for ExchangeSvc<T> {
+                        type Response = super::ExchangeReply;
+                        type ResponseStream = T::ExchangeStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::Message>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::exchange(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExchangeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/JoinSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct JoinSessionSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionId> for JoinSessionSvc<T> {
+                        type Response = super::JoinReply;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionId>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::join_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = JoinSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/CompleteSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct CompleteSessionSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionId> for CompleteSessionSvc<T> {
+                        type Response = super::Void;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionId>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::complete_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CompleteSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/AbortSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct AbortSessionSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::AbortRequest> for AbortSessionSvc<T> {
+                        type Response = super::Void;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AbortRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::abort_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AbortSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/GetEquivocations" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetEquivocationsSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionId>
+                        for GetEquivocationsSvc<T>
+                    {
+                        type Response = super::VecEquivocation;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionId>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::get_equivocations(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetEquivocationsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/GetSessionStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetSessionStatusSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionId>
+                        for GetSessionStatusSvc<T>
+                    {
+                        type Response = super::SessionStatus;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionId>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::get_session_status(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetSessionStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/svarog.MpcSessionManager/Ping" => {
+                    #[allow(non_camel_case_types)]
+                    struct PingSvc<T: MpcSessionManager>(pub Arc<T>);
+                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::Void> for PingSvc<T> {
+                        type Response = super::EchoMessage;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Void>) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MpcSessionManager>::ping(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PingSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: MpcSessionManager> Clone for MpcSessionManagerServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
         }
-        pub async fn outbox(
-            &mut self,
-            request: impl tonic::IntoRequest<super::VecMessage>,
-        ) -> std::result::Result<tonic::Response<super::VecMessage>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::new(
-                    tonic::Code::Unknown,
-                    format!("Service was not ready: {}", e.into()),
-                )
-            })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Outbox");
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Outbox"));
-            self.inner.unary(req, path, codec).await
+    }
+    impl<T: MpcSessionManager> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
         }
-        pub async fn ping(
-            &mut self,
-            request: impl tonic::IntoRequest<super::Void>,
-        ) -> std::result::Result<tonic::Response<super::EchoMessage>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::new(
-                    tonic::Code::Unknown,
-                    format!("Service was not ready: {}", e.into()),
-                )
-            })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static("/svarog.MpcSessionManager/Ping");
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("svarog.MpcSessionManager", "Ping"));
-            self.inner.unary(req, path, codec).await
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
         }
     }
+    impl<T: MpcSessionManager> tonic::server::NamedService for MpcSessionManagerServer<T> {
+        const NAME: &'static str = "svarog.MpcSessionManager";
+    }
 }
 /// Generated server implementations.
-pub mod mpc_session_manager_server {
+pub mod sesman_admin_server {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with MpcSessionManagerServer.
+    /// Generated trait containing gRPC methods that should be implemented for use with SesmanAdminServer.
     #[async_trait]
-    pub trait MpcSessionManager: Send + Sync + 'static {
-        async fn new_session(
+    pub trait SesmanAdmin: Send + Sync + 'static {
+        /// Sessions not expired yet, in no particular order.
+        async fn list_sessions(
             &self,
-            request: tonic::Request<super::SessionConfig>,
-        ) -> std::result::Result<tonic::Response<super::SessionId>, tonic::Status>;
-        async fn get_session_config(
+            request: tonic::Request<super::Void>,
+        ) -> std::result::Result<tonic::Response<super::VecSessionSummary>, tonic::Status>;
+        async fn inspect_session(
             &self,
             request: tonic::Request<super::SessionId>,
-        ) -> std::result::Result<tonic::Response<super::SessionConfig>, tonic::Status>;
-        async fn inbox(
+        ) -> std::result::Result<tonic::Response<super::SessionSummary>, tonic::Status>;
+        /// Remove the session and its messages at once.
+        /// Players waiting in the session fail with `NotFound`.
+        async fn purge_session(
             &self,
-            request: tonic::Request<super::VecMessage>,
+            request: tonic::Request<super::SessionId>,
         ) -> std::result::Result<tonic::Response<super::Void>, tonic::Status>;
-        async fn outbox(
-            &self,
-            request: tonic::Request<super::VecMessage>,
-        ) -> std::result::Result<tonic::Response<super::VecMessage>, tonic::Status>;
-        async fn ping(
+        /// Current resource usage of sesman, and its limits.
+        async fn get_usage(
             &self,
             request: tonic::Request<super::Void>,
-        ) -> std::result::Result<tonic::Response<super::EchoMessage>, tonic::Status>;
+        ) -> std::result::Result<tonic::Response<super::Usage>, tonic::Status>;
     }
+    /// Operational RPCs for the operators of sesman, not for the players.
+    /// Every call carries `authorization: Bearer <admin token>`.
     #[derive(Debug)]
-    pub struct MpcSessionManagerServer<T: MpcSessionManager> {
+    pub struct SesmanAdminServer<T: SesmanAdmin> {
         inner: _Inner<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
@@ -267,7 +1558,7 @@ pub mod mpc_session_manager_server {
         max_encoding_message_size: Option<usize>,
     }
     struct _Inner<T>(Arc<T>);
-    impl<T: MpcSessionManager> MpcSessionManagerServer<T> {
+    impl<T: SesmanAdmin> SesmanAdminServer<T> {
         pub fn new(inner: T) -> Self {
             Self::from_arc(Arc::new(inner))
         }
@@ -316,9 +1607,9 @@ pub mod mpc_session_manager_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for MpcSessionManagerServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for SesmanAdminServer<T>
     where
-        T: MpcSessionManager,
+        T: SesmanAdmin,
         B: Body + Send + 'static,
         B::Error: Into<StdError> + Send + 'static,
     {
@@ -334,19 +1625,16 @@ pub mod mpc_session_manager_server {
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             let inner = self.inner.clone();
             match req.uri().path() {
-                "/svarog.MpcSessionManager/NewSession" => {
+                "/svarog.SesmanAdmin/ListSessions" => {
                     #[allow(non_camel_case_types)]
-                    struct NewSessionSvc<T: MpcSessionManager>(pub Arc<T>);
-                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionConfig> for NewSessionSvc<T> {
-                        type Response = super::SessionId;
+                    struct ListSessionsSvc<T: SesmanAdmin>(pub Arc<T>);
+                    impl<T: SesmanAdmin> tonic::server::UnaryService<super::Void> for ListSessionsSvc<T> {
+                        type Response = super::VecSessionSummary;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
-                        fn call(
-                            &mut self,
-                            request: tonic::Request<super::SessionConfig>,
-                        ) -> Self::Future {
+                        fn call(&mut self, request: tonic::Request<super::Void>) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MpcSessionManager>::new_session(&inner, request).await
+                                <T as SesmanAdmin>::list_sessions(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -358,7 +1646,7 @@ pub mod mpc_session_manager_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = NewSessionSvc(inner);
+                        let method = ListSessionsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -374,13 +1662,11 @@ pub mod mpc_session_manager_server {
                     };
                     Box::pin(fut)
                 }
-                "/svarog.MpcSessionManager/GetSessionConfig" => {
+                "/svarog.SesmanAdmin/InspectSession" => {
                     #[allow(non_camel_case_types)]
-                    struct GetSessionConfigSvc<T: MpcSessionManager>(pub Arc<T>);
-                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::SessionId>
-                        for GetSessionConfigSvc<T>
-                    {
-                        type Response = super::SessionConfig;
+                    struct InspectSessionSvc<T: SesmanAdmin>(pub Arc<T>);
+                    impl<T: SesmanAdmin> tonic::server::UnaryService<super::SessionId> for InspectSessionSvc<T> {
+                        type Response = super::SessionSummary;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(
                             &mut self,
@@ -388,7 +1674,7 @@ pub mod mpc_session_manager_server {
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MpcSessionManager>::get_session_config(&inner, request).await
+                                <T as SesmanAdmin>::inspect_session(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -400,7 +1686,7 @@ pub mod mpc_session_manager_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetSessionConfigSvc(inner);
+                        let method = InspectSessionSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -416,59 +1702,19 @@ pub mod mpc_session_manager_server {
                     };
                     Box::pin(fut)
                 }
-                "/svarog.MpcSessionManager/Inbox" => {
+                "/svarog.SesmanAdmin/PurgeSession" => {
                     #[allow(non_camel_case_types)]
-                    struct InboxSvc<T: MpcSessionManager>(pub Arc<T>);
-                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::VecMessage> for InboxSvc<T> {
+                    struct PurgeSessionSvc<T: SesmanAdmin>(pub Arc<T>);
+                    impl<T: SesmanAdmin> tonic::server::UnaryService<super::SessionId> for PurgeSessionSvc<T> {
                         type Response = super::Void;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::VecMessage>,
-                        ) -> Self::Future {
-                            let inner = Arc::clone(&self.0);
-                            let fut = async move {
-                                <T as MpcSessionManager>::inbox(&inner, request).await
-                            };
-                            Box::pin(fut)
-                        }
-                    }
-                    let accept_compression_encodings = self.accept_compression_encodings;
-                    let send_compression_encodings = self.send_compression_encodings;
-                    let max_decoding_message_size = self.max_decoding_message_size;
-                    let max_encoding_message_size = self.max_encoding_message_size;
-                    let inner = self.inner.clone();
-                    let fut = async move {
-                        let inner = inner.0;
-                        let method = InboxSvc(inner);
-                        let codec = tonic::codec::ProstCodec::default();
-                        let mut grpc = tonic::server::Grpc::new(codec)
-                            .apply_compression_config(
-                                accept_compression_encodings,
-                                send_compression_encodings,
-                            )
-                            .apply_max_message_size_config(
-                                max_decoding_message_size,
-                                max_encoding_message_size,
-                            );
-                        let res = grpc.unary(method, req).await;
-                        Ok(res)
-                    };
-                    Box::pin(fut)
-                }
-                "/svarog.MpcSessionManager/Outbox" => {
-                    #[allow(non_camel_case_types)]
-                    struct OutboxSvc<T: MpcSessionManager>(pub Arc<T>);
-                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::VecMessage> for OutboxSvc<T> {
-                        type Response = super::VecMessage;
-                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
-                        fn call(
-                            &mut self,
-                            request: tonic::Request<super::VecMessage>,
+                            request: tonic::Request<super::SessionId>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MpcSessionManager>::outbox(&inner, request).await
+                                <T as SesmanAdmin>::purge_session(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -480,7 +1726,7 @@ pub mod mpc_session_manager_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = OutboxSvc(inner);
+                        let method = PurgeSessionSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -496,17 +1742,16 @@ pub mod mpc_session_manager_server {
                     };
                     Box::pin(fut)
                 }
-                "/svarog.MpcSessionManager/Ping" => {
+                "/svarog.SesmanAdmin/GetUsage" => {
                     #[allow(non_camel_case_types)]
-                    struct PingSvc<T: MpcSessionManager>(pub Arc<T>);
-                    impl<T: MpcSessionManager> tonic::server::UnaryService<super::Void> for PingSvc<T> {
-                        type Response = super::EchoMessage;
+                    struct GetUsageSvc<T: SesmanAdmin>(pub Arc<T>);
+                    impl<T: SesmanAdmin> tonic::server::UnaryService<super::Void> for GetUsageSvc<T> {
+                        type Response = super::Usage;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(&mut self, request: tonic::Request<super::Void>) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move {
-                                <T as MpcSessionManager>::ping(&inner, request).await
-                            };
+                            let fut =
+                                async move { <T as SesmanAdmin>::get_usage(&inner, request).await };
                             Box::pin(fut)
                         }
                     }
@@ -517,7 +1762,7 @@ pub mod mpc_session_manager_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = PingSvc(inner);
+                        let method = GetUsageSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -544,7 +1789,7 @@ pub mod mpc_session_manager_server {
             }
         }
     }
-    impl<T: MpcSessionManager> Clone for MpcSessionManagerServer<T> {
+    impl<T: SesmanAdmin> Clone for SesmanAdminServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -556,7 +1801,7 @@ pub mod mpc_session_manager_server {
             }
         }
     }
-    impl<T: MpcSessionManager> Clone for _Inner<T> {
+    impl<T: SesmanAdmin> Clone for _Inner<T> {
         fn clone(&self) -> Self {
             Self(Arc::clone(&self.0))
         }
@@ -566,7 +1811,7 @@ pub mod mpc_session_manager_server {
             write!(f, "{:?}", self.0)
         }
     }
-    impl<T: MpcSessionManager> tonic::server::NamedService for MpcSessionManagerServer<T> {
-        const NAME: &'static str = "svarog.MpcSessionManager";
+    impl<T: SesmanAdmin> tonic::server::NamedService for SesmanAdminServer<T> {
+        const NAME: &'static str = "svarog.SesmanAdmin";
     }
 }
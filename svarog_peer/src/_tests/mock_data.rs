@@ -45,6 +45,7 @@ pub fn mock_keygen_config(th: usize, players: &[&str], sesman_url: &str) -> Sess
     config.sesman_url = sesman_url.to_owned();
     config.threshold = th as u64;
     config.players = players.iter().map(|s| (s.to_string(), true)).collect();
+    config.kind = SessionKind::Keygen as i32;
     config
 }
 
@@ -73,6 +74,7 @@ pub fn mock_sign_config(th: usize, players: &[&str], sesman_url: &str) -> Sessio
         }
     }
     config.players = signers;
+    config.kind = SessionKind::Sign as i32;
 
     config
 }
@@ -93,6 +95,7 @@ pub fn mock_reshare_config(
     config.threshold = consumer_th as u64;
     config.players_reshared = _config.players;
     config.sesman_url = sesman_url.to_owned();
+    config.kind = SessionKind::Reshare as i32;
 
     let provider_set: BTreeSet<String> = {
         let mut res = BTreeSet::new();
@@ -14,6 +14,7 @@ const sesman_url: &str = "http://127.0.0.1:2000";
 /// 集成测试普通的keygen, sign
 #[tokio::main]
 async fn main() -> Resultat<()> {
+    svarog_peer::init_tracing(false, "info").catch_()?;
     println!("{}", svarog_peer::version());
     println!("{}", svarog_algo::version());
     test_btc().await.catch_()?;
@@ -28,7 +29,12 @@ async fn test_btc() -> Resultat<()> {
 
         let mut threads = BTreeMap::new();
         for (player, _) in cfg.players.iter() {
-            let future = btc::biz_keygen(sesman_url.to_owned(), sid.clone(), player.clone());
+            let future = btc::biz_keygen(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -51,7 +57,8 @@ async fn test_btc() -> Resultat<()> {
             let keystore = keystores.get(player).ifnone_()?;
             let future = btc::biz_sign(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 keystore.clone(),
                 mock_sign_tasks(),
             );
@@ -98,7 +105,12 @@ async fn test_solana() -> Resultat<()> {
 
         let mut threads = BTreeMap::new();
         for (player, _) in cfg.players.iter() {
-            let future = solana::biz_keygen(sesman_url.to_owned(), sid.clone(), player.clone());
+            let future = solana::biz_keygen(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -121,7 +133,8 @@ async fn test_solana() -> Resultat<()> {
             let keystore = keystores.get(player).ifnone_()?;
             let future = solana::biz_sign(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 keystore.clone(),
                 mock_sign_tasks(),
             );
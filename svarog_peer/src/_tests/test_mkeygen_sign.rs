@@ -3,7 +3,7 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use erreur::*;
 use mock_data::{mock_mnem, mock_sign_tasks};
-use svarog_peer::{btc, new_session, solana};
+use svarog_peer::{btc, new_session, solana, structs::SessionKind};
 
 // 改成通配符引用之后, 会难以检查到底用了哪些符号. 通配符看着优雅, 但是不利于代码审查.
 use crate::mock_data::{mock_keygen_config, mock_sign_config, players1, th1};
@@ -14,6 +14,7 @@ const sesman_url: &str = "http://127.0.0.1:2000";
 /// 集成测试普通的keygen, sign
 #[tokio::main]
 async fn main() -> Resultat<()> {
+    svarog_peer::init_tracing(false, "info").catch_()?;
     test_btc().await.catch_()?;
     test_solana().await.catch_()?;
     Ok(())
@@ -21,14 +22,16 @@ async fn main() -> Resultat<()> {
 
 async fn test_btc() -> Resultat<()> {
     let keystores = {
-        let cfg = mock_keygen_config(th1, &players1, sesman_url);
+        let mut cfg = mock_keygen_config(th1, &players1, sesman_url);
+        cfg.kind = SessionKind::KeygenMnem as i32;
         let sid = new_session(cfg.clone()).await.catch_()?;
 
         let mut threads = BTreeMap::new();
         '_mnem_provider: {
             let future = btc::biz_keygen_mnem(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get("").ifnone_()?.clone(),
                 "".to_owned(),
                 Some(mock_mnem()),
             );
@@ -36,8 +39,13 @@ async fn test_btc() -> Resultat<()> {
             threads.insert("".to_owned(), thread);
         }
         for (player, _) in cfg.players.iter() {
-            let future =
-                btc::biz_keygen_mnem(sesman_url.to_owned(), sid.clone(), player.clone(), None);
+            let future = btc::biz_keygen_mnem(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+                None,
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -71,7 +79,8 @@ async fn test_btc() -> Resultat<()> {
             let keystore = keystores.get(player).ifnone_()?;
             let future = btc::biz_sign(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 keystore.clone(),
                 mock_sign_tasks(),
             );
@@ -97,14 +106,16 @@ async fn test_btc() -> Resultat<()> {
 
 async fn test_solana() -> Resultat<()> {
     let keystores = {
-        let cfg = mock_keygen_config(th1, &players1, sesman_url);
+        let mut cfg = mock_keygen_config(th1, &players1, sesman_url);
+        cfg.kind = SessionKind::KeygenMnem as i32;
         let sid = new_session(cfg.clone()).await.catch_()?;
 
         let mut threads = BTreeMap::new();
         '_mnem_provider: {
             let future = solana::biz_keygen_mnem(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get("").ifnone_()?.clone(),
                 "".to_owned(),
                 Some(mock_mnem()),
             );
@@ -112,8 +123,13 @@ async fn test_solana() -> Resultat<()> {
             threads.insert("".to_owned(), thread);
         }
         for (player, _) in cfg.players.iter() {
-            let future =
-                solana::biz_keygen_mnem(sesman_url.to_owned(), sid.clone(), player.clone(), None);
+            let future = solana::biz_keygen_mnem(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+                None,
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -147,7 +163,8 @@ async fn test_solana() -> Resultat<()> {
             let keystore = keystores.get(player).ifnone_()?;
             let future = solana::biz_sign(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 keystore.clone(),
                 mock_sign_tasks(),
             );
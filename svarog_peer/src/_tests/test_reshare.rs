@@ -15,6 +15,7 @@ const sesman_url: &str = "http://127.0.0.1:2000";
 /// 集成测试普通的keygen, sign
 #[tokio::main]
 async fn main() -> Resultat<()> {
+    svarog_peer::init_tracing(false, "info").catch_()?;
     test_btc().await.catch_()?;
     test_solana().await.catch_()?;
     Ok(())
@@ -26,7 +27,12 @@ async fn test_btc() -> Resultat<()> {
         let sid = new_session(cfg.clone()).await.catch_()?;
         let mut threads = BTreeMap::new();
         for (player, _) in cfg.players.iter() {
-            let future = btc::biz_keygen(sesman_url.to_owned(), sid.clone(), player.clone());
+            let future = btc::biz_keygen(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -52,7 +58,8 @@ async fn test_btc() -> Resultat<()> {
             let keystore = keystores_old.get(player).ifnone_()?;
             let future = btc::biz_reshare(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 player.clone(),
                 Some(keystore.clone()),
             );
@@ -62,7 +69,13 @@ async fn test_btc() -> Resultat<()> {
 
         // spawn threads for reshare consumers not in providers
         for player in exclusive_consumers.iter() {
-            let future = btc::biz_reshare(sesman_url.to_owned(), sid.clone(), player.clone(), None);
+            let future = btc::biz_reshare(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+                None,
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -86,7 +99,8 @@ async fn test_btc() -> Resultat<()> {
             let keystore = keystores.get(player).ifnone_()?.as_ref().ifnone_()?;
             let future = btc::biz_sign(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 keystore.clone(),
                 mock_sign_tasks(),
             );
@@ -115,7 +129,12 @@ async fn test_solana() -> Resultat<()> {
         let sid = new_session(cfg.clone()).await.catch_()?;
         let mut threads = BTreeMap::new();
         for (player, _) in cfg.players.iter() {
-            let future = solana::biz_keygen(sesman_url.to_owned(), sid.clone(), player.clone());
+            let future = solana::biz_keygen(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -141,7 +160,8 @@ async fn test_solana() -> Resultat<()> {
             let keystore = keystores_old.get(player).ifnone_()?;
             let future = solana::biz_reshare(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 player.clone(),
                 Some(keystore.clone()),
             );
@@ -151,8 +171,13 @@ async fn test_solana() -> Resultat<()> {
 
         // spawn threads for reshare consumers not in providers
         for player in exclusive_consumers.iter() {
-            let future =
-                solana::biz_reshare(sesman_url.to_owned(), sid.clone(), player.clone(), None);
+            let future = solana::biz_reshare(
+                sesman_url.to_owned(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
+                player.clone(),
+                None,
+            );
             let thread = tokio::spawn(future);
             threads.insert(player.clone(), thread);
         }
@@ -176,7 +201,8 @@ async fn test_solana() -> Resultat<()> {
             let keystore = keystores.get(player).ifnone_()?.as_ref().ifnone_()?;
             let future = solana::biz_sign(
                 sesman_url.to_owned(),
-                sid.clone(),
+                sid.value.clone(),
+                sid.tokens.get(player).ifnone_()?.clone(),
                 keystore.clone(),
                 mock_sign_tasks(),
             );
@@ -1,26 +1,109 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use erreur::*;
-use svarog_grpc::SessionConfig;
-use svarog_sesman::SvarogChannel;
+use svarog_grpc::{Equivocation, SessionConfig, SessionId, SessionKind, SessionStatus};
+pub use svarog_sesman::{
+    init_tracing, is_broadcast_inconsistent, is_message_forged, is_session_aborted,
+    is_session_config_invalid, is_session_timeout,
+};
+use svarog_sesman::{
+    same_sesman_url, validate_session_config, SvarogChannel, ERR_INVALID_SESSION_CONFIG,
+};
 
 pub mod btc;
 pub use btc as eth;
 pub mod solana;
 pub mod structs;
 
-pub async fn new_session(cfg: SessionConfig) -> Resultat<String> {
+/// Return the session id, along with the token of each player keyed by the name.
+/// The token keyed by the empty name is for the mnemonic provider of KeygenMnem.
+/// `cfg.kind` is required, so that the config is checked for the operation up front.
+pub async fn new_session(cfg: SessionConfig) -> Resultat<SessionId> {
     assert_throw!(cfg.sesman_url.starts_with("http://") || cfg.sesman_url.starts_with("https://"));
     let https = cfg.sesman_url.starts_with("https://");
+    (cfg.kind() != SessionKind::Unspecified)
+        .then_some(())
+        .ifnone(ERR_INVALID_SESSION_CONFIG, "kind is unspecified")?;
+    validate_session_config(&cfg, cfg.kind()).catch_()?;
 
-    let chan = SvarogChannel::new_session(&cfg, &cfg.sesman_url, https)
+    let sid = SvarogChannel::new_session(&cfg, &cfg.sesman_url, https)
         .await
         .catch_()?;
-    let sid = chan.sid().to_owned();
 
     Ok(sid)
 }
 
+/// Every participant of the session fails immediately with an error
+/// for which `is_session_aborted` holds.
+pub async fn abort_session(
+    sesman_url: String,
+    session_id: String,
+    token: String,
+    reason: String,
+) -> Resultat<()> {
+    assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
+    let https = sesman_url.starts_with("https://");
+
+    SvarogChannel::abort_session(&session_id, &token, &sesman_url, https, &reason)
+        .await
+        .catch_()?;
+    Ok(())
+}
+
+/// The players who sent different messages to the same slot of the session.
+/// Any token of the session will do.
+pub async fn get_equivocations(
+    sesman_url: String,
+    session_id: String,
+    token: String,
+) -> Resultat<Vec<Equivocation>> {
+    assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
+    let https = sesman_url.starts_with("https://");
+
+    let equivocations = SvarogChannel::get_equivocations(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    Ok(equivocations)
+}
+
+/// The messages posted to the session so far, and those being waited for.
+/// Useful to find out who holds up a stuck session. Any token of the session will do.
+pub async fn get_session_status(
+    sesman_url: String,
+    session_id: String,
+    token: String,
+) -> Resultat<SessionStatus> {
+    assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
+    let https = sesman_url.starts_with("https://");
+
+    let status = SvarogChannel::get_session_status(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    Ok(status)
+}
+
+/// Check the config of the session for the operation, with the same checks as sesman
+/// does in `NewSession`, before joining it, so that an unfit session is left alone.
+async fn check_session(
+    session_id: &str,
+    sesman_url: &str,
+    https: bool,
+    kind: SessionKind,
+) -> Resultat<()> {
+    let cfg = SvarogChannel::get_session_config(session_id, sesman_url, https)
+        .await
+        .catch_()?;
+    validate_session_config(&cfg, kind).catch_()?;
+    let url = &cfg.sesman_url;
+    (url.is_empty() || same_sesman_url(url, sesman_url))
+        .then_some(())
+        .ifnone(
+            ERR_INVALID_SESSION_CONFIG,
+            format!("Session is at sesman {}, not at {}", url, sesman_url),
+        )?;
+    Ok(())
+}
+
 fn ses_arch(name: &str, names: &HashMap<String, bool>) -> (usize, BTreeSet<usize>) {
     let names: BTreeMap<String, bool> = names.iter().map(|(k, v)| (k.clone(), *v)).collect();
     let mut i = 0;
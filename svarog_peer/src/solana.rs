@@ -11,48 +11,70 @@ use svarog_algo::{
         sign_batch, ImportedPartyKey, KeystoreSchnorr,
     },
 };
+use svarog_grpc::SessionKind;
 use svarog_sesman::SvarogChannel;
+use tracing::{field::Empty, info, instrument, Span};
 
 use crate::{
-    ses_arch,
+    check_session, ses_arch,
     structs::{Mnemonics, SignTask, Signature},
 };
 
+#[instrument(
+    skip_all,
+    fields(op = "solana::keygen", session_id = %session_id, player = Empty)
+)]
 pub async fn biz_keygen(
     sesman_url: String,
     session_id: String,
+    token: String,
     member_name: String,
 ) -> Resultat<KeystoreSchnorr> {
     assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
     let https = sesman_url.starts_with("https://");
-
-    let (chan, cfg) = SvarogChannel::use_session(&session_id, &sesman_url, https)
+    check_session(&session_id, &sesman_url, https, SessionKind::Keygen)
+        .await
+        .catch_()?;
+    let (chan, cfg) = SvarogChannel::use_session(&session_id, &token, &sesman_url, https)
         .await
         .catch_()?;
     let t = cfg.threshold as usize;
     let (i, players) = ses_arch(&member_name, &cfg.players);
+    Span::current().record("player", i);
     assert_throw!(
         players.len() == cfg.players.len(),
         "all keygen members should attend"
     );
     let keystore = impl_keygen(chan, i, t, players).await.catch_()?;
+    SvarogChannel::complete_session(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    info!("completed");
     Ok(keystore)
 }
 
+#[instrument(
+    skip_all,
+    fields(op = "solana::keygen_mnem", session_id = %session_id, player = Empty)
+)]
 pub async fn biz_keygen_mnem(
     sesman_url: String,
     session_id: String,
+    token: String,
     member_name: String,
     mnemonics: Option<Mnemonics>,
 ) -> Resultat<Option<KeystoreSchnorr>> {
     assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
     let https = sesman_url.starts_with("https://");
-
-    let (chan, cfg) = SvarogChannel::use_session(&session_id, &sesman_url, https)
+    check_session(&session_id, &sesman_url, https, SessionKind::KeygenMnem)
+        .await
+        .catch_()?;
+    let (chan, cfg) = SvarogChannel::use_session(&session_id, &token, &sesman_url, https)
         .await
         .catch_()?;
     let t = cfg.threshold as usize;
     let (i, players) = ses_arch(&member_name, &cfg.players);
+    Span::current().record("player", i);
     assert_throw!(
         players.len() == cfg.players.len(),
         "all keygen members should attend"
@@ -60,23 +82,35 @@ pub async fn biz_keygen_mnem(
     let keystore = impl_keygen_mnem(chan, i, t, players, mnemonics)
         .await
         .catch_()?;
+    SvarogChannel::complete_session(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    info!("completed");
     Ok(keystore)
 }
 
+#[instrument(
+    skip_all,
+    fields(op = "solana::keygen_mnemi", session_id = %session_id, player = Empty)
+)]
 pub async fn biz_keygen_mnemi(
     sesman_url: String,
     session_id: String,
+    token: String,
     member_name: String,
     mnem_i: String,
 ) -> Resultat<KeystoreSchnorr> {
     assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
     let https = sesman_url.starts_with("https://");
-
-    let (chan, cfg) = SvarogChannel::use_session(&session_id, &sesman_url, https)
+    check_session(&session_id, &sesman_url, https, SessionKind::Keygen)
+        .await
+        .catch_()?;
+    let (chan, cfg) = SvarogChannel::use_session(&session_id, &token, &sesman_url, https)
         .await
         .catch_()?;
     let t = cfg.threshold as usize;
     let (i, players) = ses_arch(&member_name, &cfg.players);
+    Span::current().record("player", i);
     assert_throw!(
         players.len() == cfg.players.len(),
         "all keygen members should attend"
@@ -84,47 +118,77 @@ pub async fn biz_keygen_mnemi(
     let keystore = impl_keygen_mnemi(chan, i, t, players, mnem_i)
         .await
         .catch_()?;
+    SvarogChannel::complete_session(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    info!("completed");
     Ok(keystore)
 }
 
+#[instrument(
+    skip_all,
+    fields(op = "solana::sign", session_id = %session_id, player = Empty)
+)]
 pub async fn biz_sign(
     sesman_url: String,
     session_id: String,
+    token: String,
     keystore: KeystoreSchnorr,
     tasks: Vec<SignTask>,
 ) -> Resultat<Vec<Signature>> {
     assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
     let https = sesman_url.starts_with("https://");
-
-    let (chan, cfg) = SvarogChannel::use_session(&session_id, &sesman_url, https)
+    check_session(&session_id, &sesman_url, https, SessionKind::Sign)
+        .await
+        .catch_()?;
+    let (chan, cfg) = SvarogChannel::use_session(&session_id, &token, &sesman_url, https)
         .await
         .catch_()?;
     let (_, signers) = ses_arch("", &cfg.players);
     let i = keystore.i as usize;
+    Span::current().record("player", i);
     assert_throw!(signers.contains(&i), "signer not in the session");
     let sigs = impl_sign(chan, keystore, signers, tasks).await.catch_()?;
+    SvarogChannel::complete_session(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    info!("completed");
     Ok(sigs)
 }
 
+#[instrument(
+    skip_all,
+    fields(
+        op = "solana::reshare",
+        session_id = %session_id,
+        player = Empty,
+        provider = Empty
+    )
+)]
 pub async fn biz_reshare(
     sesman_url: String,
     session_id: String,
+    token: String,
     member_name: String,
     keystore: Option<KeystoreSchnorr>,
 ) -> Resultat<Option<KeystoreSchnorr>> {
     assert_throw!(sesman_url.starts_with("http://") || sesman_url.starts_with("https://"));
     let https = sesman_url.starts_with("https://");
-
-    let (chan, cfg) = SvarogChannel::use_session(&session_id, &sesman_url, https)
+    check_session(&session_id, &sesman_url, https, SessionKind::Reshare)
+        .await
+        .catch_()?;
+    let (chan, cfg) = SvarogChannel::use_session(&session_id, &token, &sesman_url, https)
         .await
         .catch_()?;
     let t = cfg.threshold as usize;
     let (_, providers) = ses_arch("", &cfg.players);
     if let Some(keystore) = &keystore {
         let i0 = keystore.i as usize;
+        Span::current().record("provider", i0);
         assert_throw!(providers.contains(&i0), "provider not in the session");
     }
     let (i, consumers) = ses_arch(&member_name, &cfg.players_reshared);
+    Span::current().record("player", i);
     assert_throw!(
         consumers.len() == cfg.players_reshared.len(),
         "all keygen members should attend"
@@ -132,6 +196,10 @@ pub async fn biz_reshare(
     let keystore = impl_reshare(chan, keystore, i, t, providers, consumers)
         .await
         .catch_()?;
+    SvarogChannel::complete_session(&session_id, &token, &sesman_url, https)
+        .await
+        .catch_()?;
+    info!("completed");
     Ok(keystore)
 }
 
@@ -28,4 +28,4 @@ pub struct Signature {
 
 pub use svarog_algo::elgamal_secp256k1::KeystoreElgamal;
 pub use svarog_algo::schnorr_ed25519::KeystoreSchnorr;
-pub use svarog_grpc::SessionConfig;
+pub use svarog_grpc::{Equivocation, SessionConfig, SessionId, SessionKind, SessionStatus};
@@ -0,0 +1,110 @@
+use clap::{Arg, ArgAction, Command};
+use erreur::*;
+use svarog_grpc::{SessionState, SessionSummary};
+use svarog_sesman::SvarogAdmin;
+
+fn state_name(summary: &SessionSummary) -> &'static str {
+    SessionState::try_from(summary.state)
+        .map(|state| state.as_str_name())
+        .unwrap_or("UNKNOWN")
+}
+
+fn print_sessions(mut sessions: Vec<SessionSummary>) {
+    sessions.sort_by_key(|summary| summary.created_at);
+    println!(
+        "{:<40} {:<10} {:>8} {:>8} {:>10} {:>8} {:>9} {:>7}",
+        "SESSION_ID", "STATE", "AGE_S", "MSGS", "BYTES", "JOINED", "COMPLETED", "WAITING"
+    );
+    for summary in sessions.iter() {
+        let sid = summary
+            .config
+            .as_ref()
+            .map(|cfg| cfg.session_id.as_str())
+            .unwrap_or_default();
+        println!(
+            "{:<40} {:<10} {:>8} {:>8} {:>10} {:>8} {:>9} {:>7}",
+            sid,
+            state_name(summary),
+            summary.age_ms / 1000,
+            summary.messages,
+            summary.bytes,
+            format!("{}/{}", summary.joined, summary.participants),
+            format!("{}/{}", summary.completed, summary.participants),
+            summary.waiting,
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Resultat<()> {
+    // Parse args
+    let matches = Command::new("svarog_admin")
+        .about("Operate svarog_sesman through its SesmanAdmin service.")
+        .arg(
+            Arg::new("url")
+                .short('u')
+                .long("url")
+                .required(false)
+                .default_value("http://127.0.0.1:2000")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("token_file")
+                .long("token-file")
+                .required(false)
+                .help("File of the admin token. Fall back to env SVAROG_ADMIN_TOKEN if omitted.")
+                .action(ArgAction::Set),
+        )
+        .subcommand_required(true)
+        .subcommand(Command::new("list").about("List the sessions not expired yet."))
+        .subcommand(
+            Command::new("inspect")
+                .about("Show the config and progress of a session.")
+                .arg(Arg::new("session_id").required(true)),
+        )
+        .subcommand(
+            Command::new("purge")
+                .about("Remove a session and its messages at once.")
+                .arg(Arg::new("session_id").required(true)),
+        )
+        .subcommand(Command::new("usage").about("Show the resource usage and the limits."))
+        .get_matches();
+    let url: String = matches.get_one::<String>("url").ifnone_()?.to_owned();
+    assert_throw!(url.starts_with("http://") || url.starts_with("https://"));
+    let https = url.starts_with("https://");
+    let token = match matches.get_one::<String>("token_file") {
+        Some(path) => std::fs::read_to_string(path)
+            .catch("", format!("Try reading admin token from {}", path))?,
+        None => std::env::var("SVAROG_ADMIN_TOKEN").catch(
+            "",
+            "Either --token-file or env SVAROG_ADMIN_TOKEN is required",
+        )?,
+    };
+
+    let mut admin = SvarogAdmin::connect(&url, https, token.trim())
+        .await
+        .catch_()?;
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let sessions = admin.list_sessions().await.catch_()?;
+            print_sessions(sessions);
+        }
+        Some(("inspect", sub)) => {
+            let sid = sub.get_one::<String>("session_id").ifnone_()?;
+            let summary = admin.inspect_session(sid).await.catch_()?;
+            println!("{:#?}", summary);
+        }
+        Some(("purge", sub)) => {
+            let sid = sub.get_one::<String>("session_id").ifnone_()?;
+            admin.purge_session(sid).await.catch_()?;
+            println!("Session {} is purged", sid);
+        }
+        Some(("usage", _)) => {
+            let usage = admin.get_usage().await.catch_()?;
+            println!("{:#?}", usage);
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use clap::{Arg, Command};
+use erreur::*;
+use svarog_sesman::verify_audit_log;
+
+fn main() -> Resultat<()> {
+    // Parse args
+    let matches = Command::new("svarog_audit")
+        .about("Check the audit log written by svarog_sesman --audit-log.")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("verify")
+                .about("Check the hash chain of the log, and print the hash of its last record.")
+                .arg(Arg::new("file").required(true)),
+        )
+        .get_matches();
+
+    if let Some(("verify", sub)) = matches.subcommand() {
+        let path = sub.get_one::<String>("file").ifnone_()?;
+        let (n, head) = verify_audit_log(path).catch_()?;
+        println!("{} records chained, head {}", n, head);
+    }
+    Ok(())
+}
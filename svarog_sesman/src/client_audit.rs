@@ -0,0 +1,190 @@
+//! Audit log of sesman, as written by `svarog_sesman --audit-log` and checked by `svarog_audit`.
+//!
+//! Every line is a JSON record of a session event, followed by the SHA-256 of the line
+//! up to there as `"hash"`. Each record carries the hash of the previous line as `"prev"`,
+//! and its position as `"seq"`, so that editing, inserting, dropping or reordering lines
+//! breaks the chain from there on. Payloads of messages never appear in the log.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use erreur::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use svarog_grpc::SessionConfig;
+
+/// `prev` of the first record.
+pub const AUDIT_GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Title of the error raised when the audit log fails the check.
+pub const ERR_AUDIT_CHAIN_BROKEN: &str = "AuditChainBroken";
+
+const HASH_FIELD: &str = ",\"hash\":\"";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    /// Unix time in milliseconds.
+    pub time: u64,
+    pub event: String,
+    pub session_id: String,
+    /// The player who caused the event, if any.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub player: String,
+    /// Fields specific to the event.
+    #[serde(default)]
+    pub detail: serde_json::Value,
+    pub prev: String,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// SHA-256 of the canonical JSON of the config, with the keys of every map sorted,
+/// so that the players can check the config sesman recorded against their own.
+pub fn config_digest(cfg: &SessionConfig) -> Resultat<String> {
+    let canonical = serde_json::to_value(cfg).catch_()?;
+    Ok(sha256_hex(&serde_json::to_vec(&canonical).catch_()?))
+}
+
+impl AuditRecord {
+    /// The line of the record, without the trailing newline, and its hash.
+    pub fn to_line(&self) -> Resultat<(String, String)> {
+        let body = serde_json::to_string(self).catch_()?;
+        let hash = sha256_hex(body.as_bytes());
+        let line = format!("{}{}{}\"}}", &body[..body.len() - 1], HASH_FIELD, &hash);
+        Ok((line, hash))
+    }
+
+    /// Parse a line, and check it against the hash it ends with.
+    pub fn from_line(line: &str) -> Resultat<(Self, String)> {
+        let malformed = || format!("Malformed audit record {:?}", line);
+        let at = line
+            .rfind(HASH_FIELD)
+            .filter(|_| line.ends_with("\"}"))
+            .ifnone(ERR_AUDIT_CHAIN_BROKEN, malformed())?;
+        let hash = &line[at + HASH_FIELD.len()..line.len() - 2];
+        let body = format!("{}}}", &line[..at]);
+        assert_throw!(
+            sha256_hex(body.as_bytes()) == hash,
+            format!("Audit record does not match its hash {}", hash)
+        );
+        let rec: Self = serde_json::from_str(&body).catch(ERR_AUDIT_CHAIN_BROKEN, malformed())?;
+        Ok((rec, hash.to_owned()))
+    }
+}
+
+/// The last record of the log and its hash, to chain the next record to.
+pub fn last_audit_record(path: &str) -> Resultat<Option<(AuditRecord, String)>> {
+    let file = File::open(path).catch("", format!("Try reading {}", path))?;
+    let mut last = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.catch("", format!("Try reading {}", path))?;
+        if !line.is_empty() {
+            last = Some(line);
+        }
+    }
+    match last {
+        Some(line) => Ok(Some(AuditRecord::from_line(&line).catch_()?)),
+        None => Ok(None),
+    }
+}
+
+/// Check the hash chain of the whole log, and return the number of records
+/// and the hash of the last, which can be kept elsewhere to detect truncation.
+pub fn verify_audit_log(path: &str) -> Resultat<(u64, String)> {
+    let file = File::open(path).catch("", format!("Try reading {}", path))?;
+    let mut prev = AUDIT_GENESIS.to_owned();
+    let mut n = 0u64;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.catch("", format!("Try reading {}", path))?;
+        let lineno = i + 1;
+        let (rec, hash) = AuditRecord::from_line(&line)
+            .catch(ERR_AUDIT_CHAIN_BROKEN, format!("At line {}", lineno))?;
+        let chained = rec.prev == prev && rec.seq == n;
+        chained.then_some(()).ifnone(
+            ERR_AUDIT_CHAIN_BROKEN,
+            format!(
+                "Line {} is not chained to the line before: seq {} and prev {}, expected {} and {}",
+                lineno, rec.seq, &rec.prev, n, &prev
+            ),
+        )?;
+        prev = hash;
+        n += 1;
+    }
+    Ok((n, prev))
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use serde_json::json;
+
+    use super::{verify_audit_log, AuditRecord, AUDIT_GENESIS, ERR_AUDIT_CHAIN_BROKEN};
+
+    /// Lines of a log of `n` records, chained the way sesman writes them.
+    fn chain(n: u64) -> Resultat<Vec<String>> {
+        let mut prev = AUDIT_GENESIS.to_owned();
+        let mut lines = Vec::new();
+        for seq in 0..n {
+            let rec = AuditRecord {
+                seq,
+                time: 1000 + seq,
+                event: "player_joined".to_owned(),
+                session_id: "sid".to_owned(),
+                player: format!("P{}", seq),
+                detail: json!({ "conn": "127.0.0.1:1" }),
+                prev,
+            };
+            let (line, hash) = rec.to_line().catch_()?;
+            lines.push(line);
+            prev = hash;
+        }
+        Ok(lines)
+    }
+
+    fn verify(lines: &[String]) -> Resultat<(u64, String)> {
+        let path = std::env::temp_dir().join(format!("svarog_audit_test_{}", uuid::Uuid::now_v7()));
+        let path = path.to_str().ifnone_()?;
+        std::fs::write(path, lines.join("\n") + "\n").catch_()?;
+        let res = verify_audit_log(path);
+        let _ = std::fs::remove_file(path);
+        res
+    }
+
+    fn broken(res: Resultat<(u64, String)>) -> bool {
+        res.err()
+            .is_some_and(|e| e.to_string().contains(ERR_AUDIT_CHAIN_BROKEN))
+    }
+
+    #[test]
+    fn test_verify_audit_log() -> Resultat<()> {
+        let lines = chain(3).catch_()?;
+        let (n, last) = verify(&lines).catch_()?;
+        assert_throw!(n == 3);
+
+        let mut edited = lines.clone();
+        edited[1] = edited[1].replace("\"P1\"", "\"P9\"");
+        assert_throw!(broken(verify(&edited)));
+
+        // Even if the edited line is hashed again, the next one is not chained to it.
+        let (mut rec, _) = AuditRecord::from_line(&lines[1]).catch_()?;
+        rec.player = "P9".to_owned();
+        let mut rehashed = lines.clone();
+        rehashed[1] = rec.to_line().catch_()?.0;
+        assert_throw!(broken(verify(&rehashed)));
+
+        let mut reordered = lines.clone();
+        reordered.swap(1, 2);
+        assert_throw!(broken(verify(&reordered)));
+        assert_throw!(broken(verify(&lines[1..])));
+
+        // Dropping records at the end keeps the chain, but changes the count and the
+        // last hash, to be compared with those kept elsewhere.
+        let (n_truncated, last_truncated) = verify(&lines[..2]).catch_()?;
+        assert_throw!(n_truncated == 2 && last_truncated != last);
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+//! End-to-end encryption of point-to-point messages, for sessions with `SessionConfig.e2e`.
+//!
+//! Every party of the session posts the public key of its channel under topic
+//! `E2E_KEY_TOPIC` + its name, as the first index it holds. A message to `dst` is
+//! encrypted with a content key, which is wrapped for each party holding `dst`, i.e.
+//! each party sesman would deliver the message to. Sesman only sees the ciphertext,
+//! bound to the slot of the message, so that it cannot be moved to another slot either.
+//!
+//! The channel key is derived from the identity key of the party and the session id,
+//! and the keys of a message from the channel key, its slot and its payload. So a party
+//! that rejoins posts the same public key, and a re-sent message the same ciphertext,
+//! both of which the write-once slots accept.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use ed25519_dalek::SigningKey;
+use erreur::*;
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use svarog_grpc::{Message, SessionConfig};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::{index_holders, name_indices};
+
+/// Topic prefix of the public keys. Sesman accepts a key only from the party it names.
+pub const E2E_KEY_TOPIC: &str = "__e2e_key/";
+
+const KDF_INFO: &[u8] = b"svarog_sesman e2e key wrap";
+const CHANNEL_KDF_INFO: &[u8] = b"svarog_sesman e2e channel key";
+const SEAL_KDF_INFO: &[u8] = b"svarog_sesman e2e message keys";
+
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    /// X25519 public key derived for this message.
+    #[serde(with = "serde_bytes")]
+    ephemeral: Vec<u8>,
+    /// The content key wrapped for each recipient, by name.
+    keys: BTreeMap<String, serde_bytes::ByteBuf>,
+    /// The payload encrypted with the content key.
+    #[serde(with = "serde_bytes")]
+    body: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub(crate) struct E2e {
+    player: String,
+    secret: StaticSecret,
+    /// Parties holding each index, who receive the messages sent to it.
+    holders: BTreeMap<u64, Vec<String>>,
+    /// The index each party posts its public key as.
+    key_srcs: BTreeMap<String, u64>,
+    /// Public keys of the parties, the own one included.
+    keys: HashMap<String, PublicKey>,
+}
+
+/// Associated data of a message, binding its ciphertext to its slot.
+fn slot(msg: &Message) -> Resultat<Vec<u8>> {
+    let slot = (&msg.session_id, &msg.topic, msg.src, msg.dst, msg.seq);
+    serde_pickle::to_vec(&slot, Default::default()).catch_()
+}
+
+/// Each key encrypts only one payload, so the zero nonce does.
+fn zero_nonce() -> Nonce {
+    Nonce::default()
+}
+
+fn wrapping_key(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> Resultat<Key> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+    let mut key = Key::default();
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(KDF_INFO, &mut key)
+        .catch_()?;
+    Ok(key)
+}
+
+impl E2e {
+    pub fn new(cfg: &SessionConfig, player: &str, identity: &SigningKey) -> Resultat<Self> {
+        let mut seed = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(cfg.session_id.as_bytes()), identity.as_bytes())
+            .expand_multi_info(&[CHANNEL_KDF_INFO, player.as_bytes()], &mut seed)
+            .catch_()?;
+        let secret = StaticSecret::from(seed);
+        let keys = HashMap::from([(player.to_owned(), PublicKey::from(&secret))]);
+        Ok(Self {
+            player: player.to_owned(),
+            secret,
+            holders: index_holders(cfg),
+            key_srcs: name_indices(cfg)
+                .into_iter()
+                .filter_map(|(name, indices)| Some((name, *indices.first()?)))
+                .collect(),
+            keys,
+        })
+    }
+
+    /// The ephemeral secret and the content key of a message, from its slot and payload.
+    fn message_keys(&self, aad: &[u8], plain: &[u8]) -> Resultat<(StaticSecret, Key)> {
+        let mut ha = Sha256::new();
+        ha.update((aad.len() as u64).to_be_bytes());
+        ha.update(aad);
+        ha.update(plain);
+        let mut okm = [0u8; 64];
+        Hkdf::<Sha256>::new(Some(&ha.finalize()), self.secret.as_bytes())
+            .expand(SEAL_KDF_INFO, &mut okm)
+            .catch_()?;
+        let mut ephemeral = [0u8; 32];
+        ephemeral.copy_from_slice(&okm[..32]);
+        let content_key = Key::clone_from_slice(&okm[32..]);
+        Ok((StaticSecret::from(ephemeral), content_key))
+    }
+
+    fn key_src(&self, name: &str) -> Resultat<u64> {
+        let src = self
+            .key_srcs
+            .get(name)
+            .ifnone("", format!("Player {:?} is not in the session", name))?;
+        Ok(*src)
+    }
+
+    /// The message that advertises the public key of this channel.
+    pub fn key_message(&self, sid: &str) -> Resultat<Message> {
+        Ok(Message {
+            session_id: sid.to_owned(),
+            topic: format!("{}{}", E2E_KEY_TOPIC, &self.player),
+            src: self.key_src(&self.player).catch_()?,
+            dst: 0,
+            seq: 0,
+            obj: Some(self.keys[&self.player].as_bytes().to_vec()),
+        })
+    }
+
+    fn recipients(&self, dst: u64) -> Resultat<&Vec<String>> {
+        self.holders
+            .get(&dst)
+            .ifnone("", format!("No party holds index {}", dst))
+    }
+
+    /// Requests of the public keys not known yet, to seal the messages.
+    pub fn key_requests(&self, sid: &str, msgs: &[Message]) -> Resultat<Vec<Message>> {
+        let mut names = BTreeSet::new();
+        for msg in msgs.iter().filter(|msg| msg.dst != 0) {
+            for name in self.recipients(msg.dst).catch_()? {
+                if !self.keys.contains_key(name) {
+                    names.insert(name);
+                }
+            }
+        }
+        let mut reqs = Vec::with_capacity(names.len());
+        for name in names {
+            reqs.push(Message {
+                session_id: sid.to_owned(),
+                topic: format!("{}{}", E2E_KEY_TOPIC, name),
+                src: self.key_src(name).catch_()?,
+                dst: 0,
+                seq: 0,
+                obj: None,
+            });
+        }
+        Ok(reqs)
+    }
+
+    pub fn accept_key(&mut self, msg: Message) -> Resultat<()> {
+        let name = msg.topic.strip_prefix(E2E_KEY_TOPIC).ifnone_()?;
+        let obj = msg.obj.ifnone("", "Unexpected null message")?;
+        let bytes: [u8; 32] = obj
+            .try_into()
+            .ok()
+            .ifnone("", format!("Malformed E2E key of player {:?}", name))?;
+        self.keys.insert(name.to_owned(), PublicKey::from(bytes));
+        Ok(())
+    }
+
+    /// Encrypt the payload of a point-to-point message in place.
+    /// The same message is always sealed into the same ciphertext.
+    pub fn seal(&self, msg: &mut Message) -> Resultat<()> {
+        let aad = slot(msg).catch_()?;
+        let plain = msg.obj.take().ifnone("", "Unexpected null message")?;
+        let (ephemeral, content_key) = self.message_keys(&aad, &plain).catch_()?;
+        let body = ChaCha20Poly1305::new(&content_key)
+            .encrypt(
+                &zero_nonce(),
+                Payload {
+                    msg: &plain,
+                    aad: &aad,
+                },
+            )
+            .catch("", "Failed to encrypt message")?;
+
+        let ephemeral_pk = PublicKey::from(&ephemeral);
+        let mut keys = BTreeMap::new();
+        for name in self.recipients(msg.dst).catch_()? {
+            let recipient = self
+                .keys
+                .get(name)
+                .ifnone("", format!("E2E key of player {:?} is unknown", name))?;
+            let shared = ephemeral.diffie_hellman(recipient);
+            let kek = wrapping_key(shared.as_bytes(), &ephemeral_pk, recipient).catch_()?;
+            let wrapped = ChaCha20Poly1305::new(&kek)
+                .encrypt(
+                    &zero_nonce(),
+                    Payload {
+                        msg: &content_key,
+                        aad: &aad,
+                    },
+                )
+                .catch("", "Failed to wrap content key")?;
+            keys.insert(name.clone(), serde_bytes::ByteBuf::from(wrapped));
+        }
+
+        let sealed = Sealed {
+            ephemeral: ephemeral_pk.as_bytes().to_vec(),
+            keys,
+            body,
+        };
+        msg.obj = Some(serde_pickle::to_vec(&sealed, Default::default()).catch_()?);
+        Ok(())
+    }
+
+    /// Decrypt the payload of a point-to-point message sealed for this party.
+    pub fn open(&self, msg: &Message) -> Resultat<Vec<u8>> {
+        let aad = slot(msg).catch_()?;
+        let obj = msg.obj.as_ref().ifnone("", "Unexpected null message")?;
+        let sealed: Sealed = serde_pickle::from_slice(obj, Default::default())
+            .catch("", "Message is not end-to-end encrypted")?;
+        let ephemeral: [u8; 32] = sealed
+            .ephemeral
+            .try_into()
+            .ok()
+            .ifnone("", "Malformed ephemeral key")?;
+        let ephemeral = PublicKey::from(ephemeral);
+        let wrapped = sealed.keys.get(&self.player).ifnone(
+            "",
+            format!("Message is not encrypted to player {:?}", &self.player),
+        )?;
+        let own = &self.keys[&self.player];
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        let kek = wrapping_key(shared.as_bytes(), &ephemeral, own).catch_()?;
+        let content_key = ChaCha20Poly1305::new(&kek)
+            .decrypt(
+                &zero_nonce(),
+                Payload {
+                    msg: wrapped,
+                    aad: &aad,
+                },
+            )
+            .catch("", "Failed to unwrap content key")?;
+        let content_key = Key::from_slice(&content_key);
+        let plain = ChaCha20Poly1305::new(content_key)
+            .decrypt(
+                &zero_nonce(),
+                Payload {
+                    msg: &sealed.body,
+                    aad: &aad,
+                },
+            )
+            .catch("", "Failed to decrypt message")?;
+        Ok(plain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SigningKey;
+    use erreur::*;
+    use svarog_grpc::{Message, SessionConfig};
+
+    use super::{E2e, Sealed};
+
+    fn parties() -> Resultat<(E2e, E2e, E2e)> {
+        let cfg = SessionConfig {
+            session_id: "sid".to_owned(),
+            players: [("A", true), ("B", true), ("C", true)]
+                .into_iter()
+                .map(|(name, att)| (name.to_owned(), att))
+                .collect(),
+            ..Default::default()
+        };
+        let mut parties = Vec::new();
+        for (seed, name) in [(1u8, "A"), (2, "B"), (3, "C")] {
+            parties.push(E2e::new(&cfg, name, &SigningKey::from_bytes(&[seed; 32])).catch_()?);
+        }
+        for i in 0..parties.len() {
+            for j in 0..parties.len() {
+                let key = parties[j].key_message("sid").catch_()?;
+                parties[i].accept_key(key).catch_()?;
+            }
+        }
+        let c = parties.pop().ifnone_()?;
+        let b = parties.pop().ifnone_()?;
+        let a = parties.pop().ifnone_()?;
+        Ok((a, b, c))
+    }
+
+    /// A message from A, as index 1, to B, as index 2.
+    fn message() -> Message {
+        Message {
+            session_id: "sid".to_owned(),
+            topic: "topic".to_owned(),
+            src: 1,
+            dst: 2,
+            seq: 0,
+            obj: Some(b"secret".to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_seal_open() -> Resultat<()> {
+        let (a, b, _) = parties().catch_()?;
+        let mut msg = message();
+        a.seal(&mut msg).catch_()?;
+        assert_throw!(msg.obj.as_ref() != message().obj.as_ref());
+        assert_throw!(b.open(&msg).catch_()? == b"secret".to_vec());
+
+        // A re-sent message fits its write-once slot.
+        let mut again = message();
+        a.seal(&mut again).catch_()?;
+        assert_throw!(again == msg);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejoin_keeps_key() -> Resultat<()> {
+        let (a, _, _) = parties().catch_()?;
+        let (again, _, _) = parties().catch_()?;
+        assert_throw!(a.key_message("sid")? == again.key_message("sid")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_rejects() -> Resultat<()> {
+        let (a, b, c) = parties().catch_()?;
+        let mut msg = message();
+        a.seal(&mut msg).catch_()?;
+
+        assert_throw!(c.open(&msg).is_err());
+        let mut moved = msg.clone();
+        moved.seq = 1;
+        assert_throw!(b.open(&moved).is_err());
+        let obj = msg.obj.as_ref().ifnone_()?;
+        let mut sealed: Sealed = serde_pickle::from_slice(obj, Default::default()).catch_()?;
+        sealed.body[0] ^= 1;
+        let mut tampered = msg.clone();
+        tampered.obj = Some(serde_pickle::to_vec(&sealed, Default::default()).catch_()?);
+        assert_throw!(b.open(&tampered).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! Echo of the broadcasts, for sessions with `SessionConfig.echo_broadcast`.
+//!
+//! Sesman hands a broadcast to each receiver separately, so it could hand out
+//! different payloads to different receivers. After receiving the broadcasts of a round,
+//! i.e. of a topic and seq, every player posts the digests of what it sent and received
+//! in the round under topic `ECHO_TOPIC` + the topic + `/` + its name, with the seq of
+//! the round, and compares them with the echoes of the other players, who are taken
+//! to receive the same broadcasts. The party outside of the players does not echo.
+
+use std::collections::{BTreeMap, HashMap};
+
+use erreur::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use svarog_grpc::{Message, SessionConfig};
+
+use crate::{name_indices, ERR_INCONSISTENT_BROADCAST};
+
+/// Topic prefix of the echoes.
+pub const ECHO_TOPIC: &str = "__echo/";
+
+#[derive(Serialize, Deserialize)]
+struct Echo {
+    /// `(src, SHA-256 of obj)` of each broadcast of the round sent or received.
+    digests: Vec<(u64, serde_bytes::ByteBuf)>,
+}
+
+/// What this party broadcast, to echo along with what it receives.
+#[derive(Clone)]
+pub(crate) struct Echoes {
+    player: String,
+    /// The index of each player, the own one included, that its echoes are posted as.
+    srcs: BTreeMap<String, u64>,
+    /// Digests of the broadcasts sent, by topic and seq.
+    sent: HashMap<(String, u64), BTreeMap<u64, Vec<u8>>>,
+}
+
+pub(crate) struct EchoRound {
+    topic: String,
+    seq: u64,
+    player: String,
+    srcs: BTreeMap<String, u64>,
+    /// Digests of the broadcasts of the round sent and received, by src.
+    digests: BTreeMap<u64, Vec<u8>>,
+}
+
+impl Echoes {
+    pub fn new(cfg: &SessionConfig, player: &str) -> Self {
+        Self {
+            player: player.to_owned(),
+            srcs: name_indices(cfg)
+                .into_iter()
+                .filter(|(name, _)| !name.is_empty())
+                .filter_map(|(name, indices)| Some((name, *indices.first()?)))
+                .collect(),
+            sent: HashMap::new(),
+        }
+    }
+
+    /// Note the broadcasts about to be sent, before they are signed.
+    pub fn note_sent(&mut self, msgs: &[Message]) -> Resultat<()> {
+        for msg in msgs.iter().filter(|msg| msg.dst == 0 && msg.src != 0) {
+            let obj = msg.obj.as_ref().ifnone("", "Unexpected null message")?;
+            self.sent
+                .entry((msg.topic.clone(), msg.seq))
+                .or_default()
+                .insert(msg.src, Sha256::digest(obj).to_vec());
+        }
+        Ok(())
+    }
+
+    /// The rounds of the broadcasts just received. The broadcasts of the mnemonic
+    /// provider as index 0 are not echoed.
+    pub fn rounds(&self, received: &[Message]) -> Resultat<Vec<EchoRound>> {
+        if !self.srcs.contains_key(&self.player) {
+            return Ok(Vec::new());
+        }
+        let mut rounds: BTreeMap<(&str, u64), EchoRound> = BTreeMap::new();
+        for msg in received.iter().filter(|msg| msg.dst == 0 && msg.src != 0) {
+            let obj = msg.obj.as_ref().ifnone("", "Unexpected null message")?;
+            let round = rounds
+                .entry((&msg.topic, msg.seq))
+                .or_insert_with(|| EchoRound {
+                    topic: msg.topic.clone(),
+                    seq: msg.seq,
+                    player: self.player.clone(),
+                    srcs: self.srcs.clone(),
+                    digests: self
+                        .sent
+                        .get(&(msg.topic.clone(), msg.seq))
+                        .cloned()
+                        .unwrap_or_default(),
+                });
+            round.digests.insert(msg.src, Sha256::digest(obj).to_vec());
+        }
+        Ok(rounds.into_values().collect())
+    }
+}
+
+impl EchoRound {
+    fn echo_topic(&self, name: &str) -> String {
+        format!("{}{}/{}", ECHO_TOPIC, &self.topic, name)
+    }
+
+    pub fn message(&self, sid: &str) -> Resultat<Message> {
+        let echo = Echo {
+            digests: self
+                .digests
+                .iter()
+                .map(|(&src, digest)| (src, serde_bytes::ByteBuf::from(digest.clone())))
+                .collect(),
+        };
+        Ok(Message {
+            session_id: sid.to_owned(),
+            topic: self.echo_topic(&self.player),
+            src: self.srcs[&self.player],
+            dst: 0,
+            seq: self.seq,
+            obj: Some(serde_pickle::to_vec(&echo, Default::default()).catch_()?),
+        })
+    }
+
+    /// Requests of the echoes of the other players.
+    pub fn requests(&self, sid: &str) -> Vec<Message> {
+        self.srcs
+            .iter()
+            .filter(|(name, _)| **name != self.player)
+            .map(|(name, &src)| Message {
+                session_id: sid.to_owned(),
+                topic: self.echo_topic(name),
+                src,
+                dst: 0,
+                seq: self.seq,
+                obj: None,
+            })
+            .collect()
+    }
+
+    /// The player whose echo of this round the message is, if any.
+    pub fn peer_of(&self, msg: &Message) -> Option<&str> {
+        if msg.seq != self.seq {
+            return None;
+        }
+        self.srcs
+            .iter()
+            .find(|(name, &src)| msg.src == src && msg.topic == self.echo_topic(name))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Compare the echo of a peer with what this party sent and received.
+    pub fn check(&self, msg: &Message, peer: &str) -> Resultat<()> {
+        let obj = msg.obj.as_ref().ifnone("", "Unexpected null message")?;
+        let echo: Echo = serde_pickle::from_slice(obj, Default::default()).catch(
+            ERR_INCONSISTENT_BROADCAST,
+            format!(
+                "Malformed echo of player {:?} on topic {}",
+                peer, &self.topic
+            ),
+        )?;
+        for (src, digest) in echo.digests.iter() {
+            let differs = self
+                .digests
+                .get(src)
+                .is_some_and(|own| own != digest.as_slice());
+            (!differs).then_some(()).ifnone(
+                ERR_INCONSISTENT_BROADCAST,
+                format!(
+                    "Broadcast {}-{}-0-{} differs between players {:?} and {:?}",
+                    &self.topic, src, self.seq, &self.player, peer
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use svarog_grpc::{Message, SessionConfig};
+
+    use super::Echoes;
+    use crate::is_broadcast_inconsistent;
+
+    fn echoes(player: &str) -> Echoes {
+        let cfg = SessionConfig {
+            players: [("A", true), ("B", true), ("C", true)]
+                .into_iter()
+                .map(|(name, att)| (name.to_owned(), att))
+                .collect(),
+            ..Default::default()
+        };
+        Echoes::new(&cfg, player)
+    }
+
+    fn broadcast(src: u64, seq: u64, obj: &[u8]) -> Message {
+        Message {
+            session_id: "sid".to_owned(),
+            topic: "topic".to_owned(),
+            src,
+            dst: 0,
+            seq,
+            obj: Some(obj.to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_receivers_echo_by_round() -> Resultat<()> {
+        // C only receives.
+        let c = echoes("C");
+        let rounds = c
+            .rounds(&[
+                broadcast(1, 0, b"a0"),
+                broadcast(1, 1, b"a1"),
+                broadcast(2, 1, b"b1"),
+            ])
+            .catch_()?;
+        assert_throw!(rounds.len() == 2);
+        for (round, seq) in rounds.iter().zip([0, 1]) {
+            let echo = round.message("sid").catch_()?;
+            assert_throw!((echo.topic.as_str(), echo.src, echo.seq) == ("__echo/topic/C", 3, seq));
+            let peers: Vec<(String, u64, u64)> = round
+                .requests("sid")
+                .into_iter()
+                .map(|req| (req.topic, req.src, req.seq))
+                .collect();
+            assert_throw!(
+                peers
+                    == vec![
+                        ("__echo/topic/A".to_owned(), 1, seq),
+                        ("__echo/topic/B".to_owned(), 2, seq)
+                    ]
+            );
+        }
+        // Index 0 is not echoed.
+        assert_throw!(c.rounds(&[broadcast(0, 0, b"mnem")])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_echo() -> Resultat<()> {
+        let mut a = echoes("A");
+        a.note_sent(&[broadcast(1, 0, b"a0")]).catch_()?;
+        let a_rounds = a.rounds(&[broadcast(2, 0, b"b0")]).catch_()?;
+        let c_rounds = echoes("C")
+            .rounds(&[broadcast(1, 0, b"a0"), broadcast(2, 0, b"b0")])
+            .catch_()?;
+        let (a_round, c_round) = (&a_rounds[0], &c_rounds[0]);
+        let a_echo = a_round.message("sid").catch_()?;
+        let c_echo = c_round.message("sid").catch_()?;
+        assert_throw!(c_round.peer_of(&a_echo) == Some("A"));
+        assert_throw!(a_round.peer_of(&c_echo) == Some("C"));
+        c_round.check(&a_echo, "A").catch_()?;
+        a_round.check(&c_echo, "C").catch_()?;
+
+        // B handed C another payload than it handed A.
+        let c_rounds = echoes("C")
+            .rounds(&[broadcast(1, 0, b"a0"), broadcast(2, 0, b"other")])
+            .catch_()?;
+        let c_echo = c_rounds[0].message("sid").catch_()?;
+        let res = a_round.check(&c_echo, "C");
+        assert_throw!(res.err().is_some_and(|e| is_broadcast_inconsistent(&e)));
+        Ok(())
+    }
+}
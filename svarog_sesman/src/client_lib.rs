@@ -1,23 +1,59 @@
 //! Sesman client library
 
 use std::{
-    collections::{HashMap, HashSet},
-    time::Duration,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    time::{Duration, Instant},
 };
 
+use ed25519_dalek::SigningKey;
 use erreur::*;
 use mpc_sig_abs::BatchMessenger;
 use serde::{de::DeserializeOwned, Serialize};
 use svarog_grpc::{
-    mpc_session_manager_client::MpcSessionManagerClient, Message, SessionConfig, SessionId,
-    VecMessage,
+    mpc_session_manager_client::MpcSessionManagerClient, sesman_admin_client::SesmanAdminClient,
+    AbortRequest, Equivocation, ExchangeReply, Message, SessionConfig, SessionId, SessionStatus,
+    SessionSummary, Usage, VecMessage, Void,
 };
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{
-    transport::{Certificate, Channel, ClientTlsConfig},
-    Request,
+    metadata::{Ascii, MetadataValue},
+    transport::{Certificate, Channel, ClientTlsConfig, Identity},
+    Code, Request, Status, Streaming,
 };
+use tracing::debug;
+use tracing_subscriber::EnvFilter;
 
-pub const SESSION_EXPIRE_MS: u128 = 300_000;
+mod client_audit;
+pub use client_audit::{
+    config_digest, last_audit_record, verify_audit_log, AuditRecord, AUDIT_GENESIS,
+    ERR_AUDIT_CHAIN_BROKEN,
+};
+mod client_e2e;
+mod client_echo;
+use client_e2e::E2e;
+pub use client_e2e::E2E_KEY_TOPIC;
+pub use client_echo::ECHO_TOPIC;
+use client_echo::{EchoRound, Echoes};
+mod client_sign;
+pub use client_sign::IDENTITY_KEY_FILE;
+use client_sign::{parse_identity, read_identity, Signatures};
+mod client_validate;
+pub use client_validate::{same_sesman_url, validate_session_config, ERR_INVALID_SESSION_CONFIG};
+
+/// Title of the error raised when a participant aborts the session.
+pub const ERR_SESSION_ABORTED: &str = "SessionAborted";
+/// Title of the error raised when the session expires before the messages arrive.
+pub const ERR_SESSION_TIMEOUT: &str = "SessionTimeout";
+/// Title of the error raised when a message fails the signature of its claimed sender.
+pub const ERR_FORGED_MESSAGE: &str = "ForgedMessage";
+/// Title of the error raised when receivers of a broadcast got different payloads.
+pub const ERR_INCONSISTENT_BROADCAST: &str = "InconsistentBroadcast";
+
+/// How long to wait on sesman, if it does not tell when the session expires,
+/// as older versions of sesman do not. Otherwise the wait ends when the session expires.
+pub const RECEIVE_TIMEOUT_MS: u64 = 300_000;
+const EXCHANGE_BUFFER: usize = 64;
 
 pub fn version() -> String {
     format!(
@@ -27,12 +63,180 @@ pub fn version() -> String {
     )
 }
 
-#[derive(Clone)]
+/// Install the global `tracing` subscriber, which writes to stdout.
+/// `RUST_LOG`, if set, overrides `filter`, e.g. `info,svarog_sesman=debug`.
+/// Payloads of messages are never logged.
+pub fn init_tracing(json: bool, filter: &str) -> Resultat<()> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(filter).catch("", format!("Invalid log filter {}", filter))?,
+    };
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if json {
+        let subscriber = builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .finish();
+        tracing::subscriber::set_global_default(subscriber).catch_()?;
+    } else {
+        let subscriber = builder.finish();
+        tracing::subscriber::set_global_default(subscriber).catch_()?;
+    }
+    Ok(())
+}
+
+/// Index the attending players the same way as `svarog_peer` does:
+/// the position of the name among all the sorted names, counting from 1.
+pub fn player_indices(players: &HashMap<String, bool>) -> BTreeMap<String, u64> {
+    let names: BTreeMap<&String, &bool> = players.iter().collect();
+    let mut indices = BTreeMap::new();
+    for (j, (name, &att)) in names.into_iter().enumerate() {
+        if att {
+            indices.insert(name.clone(), j as u64 + 1);
+        }
+    }
+    indices
+}
+
+/// The indices of each party, either in `players` or in `players_reshared`,
+/// plus the party outside of them, named by the empty string.
+/// Index 0 is held by the party outside only. It is used by the mnemonic provider
+/// of KeygenMnem, who takes the token of the empty name even if it is a player too,
+/// so that no player can send as the provider.
+/// Sesman delivers a message to `dst` to every party holding `dst`.
+pub fn name_indices(cfg: &SessionConfig) -> BTreeMap<String, BTreeSet<u64>> {
+    let mut indices: BTreeMap<String, BTreeSet<u64>> = BTreeMap::new();
+    indices.insert("".to_owned(), BTreeSet::from([0]));
+    for players in [&cfg.players, &cfg.players_reshared] {
+        for (name, i) in player_indices(players) {
+            indices.entry(name).or_default().insert(i);
+        }
+    }
+    indices
+}
+
+/// The parties holding each index, inverse to `name_indices`.
+fn index_holders(cfg: &SessionConfig) -> BTreeMap<u64, Vec<String>> {
+    let mut holders: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for (name, indices) in name_indices(cfg) {
+        for i in indices {
+            holders.entry(i).or_default().push(name.clone());
+        }
+    }
+    holders
+}
+
+/// Tell whether the error, possibly wrapped by callers, is due to an aborted session.
+pub fn is_session_aborted(e: &Erreur) -> bool {
+    e.to_string().contains(ERR_SESSION_ABORTED)
+}
+
+/// Tell whether the error, possibly wrapped by callers, is due to an expired session.
+pub fn is_session_timeout(e: &Erreur) -> bool {
+    e.to_string().contains(ERR_SESSION_TIMEOUT)
+}
+
+/// Tell whether the error, possibly wrapped by callers, is due to a message
+/// not signed by the party it claims to be from.
+pub fn is_message_forged(e: &Erreur) -> bool {
+    e.to_string().contains(ERR_FORGED_MESSAGE)
+}
+
+/// Whether the error is raised because a broadcast was inconsistent among its receivers.
+pub fn is_broadcast_inconsistent(e: &Erreur) -> bool {
+    e.to_string().contains(ERR_INCONSISTENT_BROADCAST)
+}
+
+/// Whether the error is raised because the config is unfit for the kind of session.
+pub fn is_session_config_invalid(e: &Erreur) -> bool {
+    e.to_string().contains(ERR_INVALID_SESSION_CONFIG)
+}
+
+trait CatchStatus<T> {
+    /// Like `catch`, but keep the aborts and the timeouts apart from other failures.
+    fn catch_status(self, api: &str) -> Resultat<T>;
+}
+
+impl<T> CatchStatus<T> for Result<T, Status> {
+    fn catch_status(self, api: &str) -> Resultat<T> {
+        let status = match self {
+            Ok(val) => return Ok(val),
+            Err(status) => status,
+        };
+        let title = match status.code() {
+            Code::Aborted => ERR_SESSION_ABORTED,
+            Code::DeadlineExceeded | Code::Cancelled => ERR_SESSION_TIMEOUT,
+            _ => "GrpcCallFailed",
+        };
+        let msg = format!("{}: {}", api, status.message());
+        Err(status).catch(title, msg)
+    }
+}
+
 pub struct SvarogChannel {
     sid: String,
+    /// `Bearer <token>`, sent along with every call.
+    auth: MetadataValue<Ascii>,
     cl: MpcSessionManagerClient<Channel>,
     tx: Vec<Message>,
     rx: HashMap<MessageIndex, Option<Vec<u8>>>,
+    /// When the session expires. Nothing is worth waiting for afterwards.
+    deadline: Instant,
+    /// The `Exchange` stream of this channel, opened on first use.
+    ex: Option<Exchange>,
+    /// Turns false once the server turns out not to support `Exchange`.
+    ex_supported: bool,
+    /// Keys of the end-to-end encryption, if the session asks for it.
+    e2e: Option<E2e>,
+    /// Identity keys of the parties, if the session pins them.
+    sig: Option<Signatures>,
+    /// The broadcasts sent, if the session asks to echo the broadcasts.
+    echo: Option<Echoes>,
+}
+
+struct Exchange {
+    tx: mpsc::Sender<Message>,
+    rx: Streaming<ExchangeReply>,
+}
+
+impl Exchange {
+    /// The next reply, failed if the post or the request it answers failed,
+    /// or if none arrives by `deadline`.
+    async fn reply(&mut self, deadline: Instant) -> Resultat<Message> {
+        let api = "MpcSessionManager::Exchange";
+        let reply = tokio::time::timeout_at(deadline.into(), self.rx.message())
+            .await
+            .catch(ERR_SESSION_TIMEOUT, api)?
+            .catch_status(api)?
+            .ifnone("GrpcCallFailed", format!("{} closed", api))?;
+        if reply.code != 0 {
+            Err(Status::new(Code::from(reply.code), reply.error)).catch_status(api)?;
+        }
+        reply
+            .msg
+            .ifnone("GrpcCallFailed", format!("{} replied nothing", api))
+    }
+}
+
+impl Clone for SvarogChannel {
+    /// The clone opens its own `Exchange` stream,
+    /// otherwise the clones would receive each other's messages.
+    fn clone(&self) -> Self {
+        Self {
+            sid: self.sid.clone(),
+            auth: self.auth.clone(),
+            cl: self.cl.clone(),
+            tx: self.tx.clone(),
+            rx: self.rx.clone(),
+            deadline: self.deadline,
+            ex: None,
+            ex_supported: self.ex_supported,
+            e2e: self.e2e.clone(),
+            sig: self.sig.clone(),
+            echo: self.echo.clone(),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -43,78 +247,509 @@ struct MessageIndex {
     seq: usize,
 }
 
-impl SvarogChannel {
-    pub fn sid(&self) -> &str {
-        &self.sid
-    }
+async fn connect(sesman_url: &str, https: bool) -> Resultat<MpcSessionManagerClient<Channel>> {
+    let ch = connect_channel(sesman_url, https).await.catch_()?;
+    Ok(MpcSessionManagerClient::new(ch))
+}
 
-    pub async fn new_session(cfg: &SessionConfig, sesman_url: &str, https: bool) -> Resultat<Self> {
-        let mut ch = Channel::from_shared(sesman_url.to_string()).catch_()?;
-        if https {
-            let pem = tokio::fs::read_to_string("tls/fullchain.pem")
+async fn connect_channel(sesman_url: &str, https: bool) -> Resultat<Channel> {
+    let mut ch = Channel::from_shared(sesman_url.to_string()).catch_()?;
+    if https {
+        let pem = tokio::fs::read_to_string("tls/fullchain.pem")
+            .await
+            .catch_()?;
+        let ca = Certificate::from_pem(pem);
+        let mut tls = ClientTlsConfig::new().ca_certificate(ca);
+        // For sesman running with mTLS.
+        if tokio::fs::try_exists("tls/client_cert.pem")
+            .await
+            .catch_()?
+        {
+            let cert = tokio::fs::read_to_string("tls/client_cert.pem")
+                .await
+                .catch_()?;
+            let key = tokio::fs::read_to_string("tls/client_privkey.pem")
                 .await
                 .catch_()?;
-            let ca = Certificate::from_pem(pem);
-            let tls = ClientTlsConfig::new().ca_certificate(ca);
-            ch = ch.tls_config(tls).catch_()?;
+            tls = tls.identity(Identity::from_pem(cert, key));
         }
-        let ch = ch
-            .connect()
+        ch = ch.tls_config(tls).catch_()?;
+    }
+    let ch = ch
+        .connect()
+        .await
+        .catch("", format!("Try connecting to {}", sesman_url))?;
+    Ok(ch)
+}
+
+fn bearer(token: &str) -> Resultat<MetadataValue<Ascii>> {
+    let auth = format!("Bearer {}", token)
+        .parse()
+        .catch("", "Token should be printable ASCII")?;
+    Ok(auth)
+}
+
+fn authorized<T>(msg: T, auth: &MetadataValue<Ascii>) -> Request<T> {
+    let mut req = Request::new(msg);
+    req.metadata_mut().insert("authorization", auth.clone());
+    req
+}
+
+/// Client of the `SesmanAdmin` service, for the operators of sesman.
+pub struct SvarogAdmin {
+    /// `Bearer <admin token>`, sent along with every call.
+    auth: MetadataValue<Ascii>,
+    cl: SesmanAdminClient<Channel>,
+}
+
+impl SvarogAdmin {
+    pub async fn connect(sesman_url: &str, https: bool, token: &str) -> Resultat<Self> {
+        let auth = bearer(token).catch_()?;
+        let ch = connect_channel(sesman_url, https).await.catch_()?;
+        Ok(Self {
+            auth,
+            cl: SesmanAdminClient::new(ch),
+        })
+    }
+
+    /// Sessions not expired yet, in no particular order.
+    pub async fn list_sessions(&mut self) -> Resultat<Vec<SessionSummary>> {
+        let sessions = self
+            .cl
+            .list_sessions(authorized(Void {}, &self.auth))
             .await
-            .catch("", format!("Try connecting to {}", sesman_url))?;
-        let mut cl = MpcSessionManagerClient::new(ch);
+            .catch_status("SesmanAdmin::ListSessions")?
+            .into_inner()
+            .values;
+        Ok(sessions)
+    }
+
+    pub async fn inspect_session(&mut self, sid: &str) -> Resultat<SessionSummary> {
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        let summary = self
+            .cl
+            .inspect_session(authorized(req, &self.auth))
+            .await
+            .catch_status("SesmanAdmin::InspectSession")?
+            .into_inner();
+        Ok(summary)
+    }
+
+    /// Remove the session and its messages at once.
+    pub async fn purge_session(&mut self, sid: &str) -> Resultat<()> {
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        self.cl
+            .purge_session(authorized(req, &self.auth))
+            .await
+            .catch_status("SesmanAdmin::PurgeSession")?;
+        Ok(())
+    }
+
+    /// Resource usage of sesman, along with its limits.
+    pub async fn get_usage(&mut self) -> Resultat<Usage> {
+        let usage = self
+            .cl
+            .get_usage(authorized(Void {}, &self.auth))
+            .await
+            .catch_status("SesmanAdmin::GetUsage")?
+            .into_inner();
+        Ok(usage)
+    }
+}
+
+impl SvarogChannel {
+    pub fn sid(&self) -> &str {
+        &self.sid
+    }
+
+    /// Return the session id, along with the token of each player.
+    /// Hand the tokens out to the players, who pass them to `use_session`.
+    pub async fn new_session(
+        cfg: &SessionConfig,
+        sesman_url: &str,
+        https: bool,
+    ) -> Resultat<SessionId> {
+        let mut cl = connect(sesman_url, https).await.catch_()?;
 
         let sid = cl
             .new_session(cfg.clone())
             .await
-            .catch("GrpcCallFailed", "MpcSessionManager::NewSession")?
-            .into_inner()
-            .value;
-        Ok(Self {
-            sid,
-            cl,
-            tx: Vec::new(),
-            rx: HashMap::new(),
-        })
+            .catch_status("MpcSessionManager::NewSession")?
+            .into_inner();
+        Ok(sid)
     }
 
+    /// If the session pins identity keys, messages are signed with the one in
+    /// `IDENTITY_KEY_FILE`, if the file exists.
     pub async fn use_session(
         sid: &str,
+        token: &str,
         sesman_url: &str,
         https: bool,
     ) -> Resultat<(Self, SessionConfig)> {
-        let mut ch = Channel::from_shared(sesman_url.to_string()).catch_()?;
-        if https {
-            let pem = tokio::fs::read_to_string("tls/fullchain.pem")
-                .await
-                .catch_()?;
-            let ca = Certificate::from_pem(pem);
-            let tls = ClientTlsConfig::new().ca_certificate(ca);
-            ch = ch.tls_config(tls).catch_()?;
-        }
-        let ch = ch
-            .connect()
-            .await
-            .catch("", format!("Try connecting to {}", sesman_url))?;
-        let mut cl = MpcSessionManagerClient::new(ch);
+        let identity = read_identity().await.catch_()?;
+        Self::join(sid, token, sesman_url, https, identity).await
+    }
+
+    /// Like `use_session`, but sign messages with the identity key given as
+    /// the hex of its 32-byte Ed25519 seed, e.g. when one process hosts several players.
+    pub async fn use_session_with_identity(
+        sid: &str,
+        token: &str,
+        sesman_url: &str,
+        https: bool,
+        identity: &str,
+    ) -> Resultat<(Self, SessionConfig)> {
+        let identity = parse_identity(identity).catch_()?;
+        Self::join(sid, token, sesman_url, https, Some(identity)).await
+    }
+
+    async fn join(
+        sid: &str,
+        token: &str,
+        sesman_url: &str,
+        https: bool,
+        identity: Option<SigningKey>,
+    ) -> Resultat<(Self, SessionConfig)> {
+        let auth = bearer(token).catch_()?;
+        let mut cl = connect(sesman_url, https).await.catch_()?;
 
         let mut req = Request::new(SessionId {
             value: sid.to_owned(),
+            ..Default::default()
         });
-        req.set_timeout(Duration::from_millis(SESSION_EXPIRE_MS as u64));
+        req.set_timeout(Duration::from_millis(RECEIVE_TIMEOUT_MS));
         let cfg: SessionConfig = cl
             .get_session_config(req)
             .await
-            .catch("GrpcCallFailed", "MpcSessionManager::GetSessionConfig")?
+            .catch_status("MpcSessionManager::GetSessionConfig")?
             .into_inner();
+        let ttl = match cfg.ttl_remaining {
+            0 => Duration::from_millis(RECEIVE_TIMEOUT_MS), // older sesman
+            ttl => Duration::from_secs(ttl),
+        };
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        let player = match cl.join_session(authorized(req, &auth)).await {
+            Err(status) if status.code() == Code::Unimplemented => None, // older sesman
+            res => Some(
+                res.catch_status("MpcSessionManager::JoinSession")?
+                    .into_inner()
+                    .player,
+            ),
+        };
+        // The channel key is derived from the identity key, which signs the public key.
+        let e2e = if cfg.e2e {
+            let player = player.as_ref().ifnone(
+                "",
+                "End-to-end encryption needs a sesman supporting JoinSession",
+            )?;
+            let identity = identity
+                .as_ref()
+                .ifnone("", "End-to-end encryption needs an identity key")?;
+            Some(E2e::new(&cfg, player, identity).catch_()?)
+        } else {
+            None
+        };
+        let echo = if cfg.echo_broadcast {
+            let player = player.as_ref().ifnone(
+                "",
+                "Echo of the broadcasts needs a sesman supporting JoinSession",
+            )?;
+            Some(Echoes::new(&cfg, player))
+        } else {
+            None
+        };
+        let sig = if cfg.player_keys.is_empty() {
+            None
+        } else {
+            let player = player
+                .as_ref()
+                .ifnone("", "Signatures need a sesman supporting JoinSession")?;
+            Some(Signatures::new(&cfg, player, identity).catch_()?)
+        };
+        if let Some(e2e) = e2e.as_ref() {
+            let mut key_msg = e2e.key_message(sid).catch_()?;
+            if let Some(sig) = sig.as_ref() {
+                sig.sign(&mut key_msg).catch_()?;
+            }
+            let req = VecMessage {
+                values: vec![key_msg],
+            };
+            // Identical to the key posted at an earlier join, if any.
+            cl.inbox(authorized(req, &auth))
+                .await
+                .catch_status("MpcSessionManager::Inbox")?;
+        }
         let _self = Self {
             sid: sid.to_string(),
+            auth,
             cl,
             tx: Vec::new(),
             rx: HashMap::new(),
+            deadline: Instant::now() + ttl,
+            ex: None,
+            ex_supported: true,
+            e2e,
+            sig,
+            echo,
         };
         Ok((_self, cfg))
     }
+
+    /// Abort the session, so that every participant fails immediately
+    /// with `ERR_SESSION_ABORTED` instead of waiting until the session expires.
+    /// Any token of the session will do.
+    pub async fn abort_session(
+        sid: &str,
+        token: &str,
+        sesman_url: &str,
+        https: bool,
+        reason: &str,
+    ) -> Resultat<()> {
+        let auth = bearer(token).catch_()?;
+        let mut cl = connect(sesman_url, https).await.catch_()?;
+        let req = AbortRequest {
+            session_id: sid.to_owned(),
+            reason: reason.to_owned(),
+        };
+        cl.abort_session(authorized(req, &auth))
+            .await
+            .catch_status("MpcSessionManager::AbortSession")?;
+        Ok(())
+    }
+
+    /// Tell sesman that the player of the token is done with the session.
+    /// The session completes, and refuses further messages, once every player is done.
+    pub async fn complete_session(
+        sid: &str,
+        token: &str,
+        sesman_url: &str,
+        https: bool,
+    ) -> Resultat<()> {
+        let auth = bearer(token).catch_()?;
+        let mut cl = connect(sesman_url, https).await.catch_()?;
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        match cl.complete_session(authorized(req, &auth)).await {
+            Err(status) if status.code() == Code::Unimplemented => {} // older sesman
+            res => {
+                res.catch_status("MpcSessionManager::CompleteSession")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The players who sent different messages to the same slot of the session.
+    /// Any token of the session will do.
+    pub async fn get_equivocations(
+        sid: &str,
+        token: &str,
+        sesman_url: &str,
+        https: bool,
+    ) -> Resultat<Vec<Equivocation>> {
+        let auth = bearer(token).catch_()?;
+        let mut cl = connect(sesman_url, https).await.catch_()?;
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        let equivocations = cl
+            .get_equivocations(authorized(req, &auth))
+            .await
+            .catch_status("MpcSessionManager::GetEquivocations")?
+            .into_inner()
+            .values;
+        Ok(equivocations)
+    }
+
+    /// The config of the session, e.g. to check it before `use_session`.
+    pub async fn get_session_config(
+        sid: &str,
+        sesman_url: &str,
+        https: bool,
+    ) -> Resultat<SessionConfig> {
+        let mut cl = connect(sesman_url, https).await.catch_()?;
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        let cfg = cl
+            .get_session_config(req)
+            .await
+            .catch_status("MpcSessionManager::GetSessionConfig")?
+            .into_inner();
+        Ok(cfg)
+    }
+
+    /// The messages posted to the session so far, and those being waited for.
+    /// Any token of the session will do.
+    pub async fn get_session_status(
+        sid: &str,
+        token: &str,
+        sesman_url: &str,
+        https: bool,
+    ) -> Resultat<SessionStatus> {
+        let auth = bearer(token).catch_()?;
+        let mut cl = connect(sesman_url, https).await.catch_()?;
+        let req = SessionId {
+            value: sid.to_owned(),
+            ..Default::default()
+        };
+        let status = cl
+            .get_session_status(authorized(req, &auth))
+            .await
+            .catch_status("MpcSessionManager::GetSessionStatus")?
+            .into_inner();
+        Ok(status)
+    }
+
+    /// Open the `Exchange` stream if not yet opened.
+    /// Return `None` if the server does not support it.
+    async fn exchange(&mut self) -> Resultat<Option<&mut Exchange>> {
+        if self.ex.is_none() && self.ex_supported {
+            let (tx, rx) = mpsc::channel(EXCHANGE_BUFFER);
+            let req = authorized(ReceiverStream::new(rx), &self.auth);
+            match self.cl.exchange(req).await {
+                Ok(resp) => {
+                    let rx = resp.into_inner();
+                    self.ex = Some(Exchange { tx, rx });
+                }
+                Err(status) if status.code() == Code::Unimplemented => {
+                    self.ex_supported = false;
+                }
+                Err(status) => {
+                    Err(status).catch_status("MpcSessionManager::Exchange")?;
+                }
+            }
+        }
+        Ok(self.ex.as_mut())
+    }
+
+    /// Encrypt the point-to-point messages end to end, if the session asks for it.
+    /// The public keys of the recipients are fetched first, unless known already.
+    async fn seal(&mut self, msgs: &mut [Message]) -> Resultat<()> {
+        let Some(e2e) = self.e2e.as_mut() else {
+            return Ok(());
+        };
+        let reqs = e2e.key_requests(&self.sid, msgs).catch_()?;
+        if !reqs.is_empty() {
+            let mut req = authorized(VecMessage { values: reqs }, &self.auth);
+            req.set_timeout(self.deadline.saturating_duration_since(Instant::now()));
+            let keys = self
+                .cl
+                .outbox(req)
+                .await
+                .catch_status("MpcSessionManager::Outbox")?
+                .into_inner()
+                .values;
+            for mut key in keys.into_iter() {
+                if let Some(sig) = self.sig.as_ref() {
+                    let name = key.topic.strip_prefix(E2E_KEY_TOPIC).unwrap_or_default();
+                    let name = name.to_owned();
+                    sig.verify_from(&mut key, &name).catch_()?;
+                }
+                e2e.accept_key(key).catch_()?;
+            }
+        }
+        for msg in msgs.iter_mut().filter(|msg| msg.dst != 0) {
+            e2e.seal(msg).catch_()?;
+        }
+        Ok(())
+    }
+
+    /// Post the echoes of the broadcasts just received, and compare them with
+    /// the echoes of the other receivers.
+    async fn echo(&mut self, rounds: Vec<EchoRound>) -> Resultat<()> {
+        if rounds.is_empty() {
+            return Ok(());
+        }
+        let mut echoes = Vec::with_capacity(rounds.len());
+        let mut reqs = Vec::new();
+        for round in rounds.iter() {
+            let mut echo = round.message(&self.sid).catch_()?;
+            if let Some(sig) = self.sig.as_ref() {
+                sig.sign(&mut echo).catch_()?;
+            }
+            echoes.push(echo);
+            reqs.extend(round.requests(&self.sid));
+        }
+        let req = authorized(VecMessage { values: echoes }, &self.auth);
+        self.cl
+            .inbox(req)
+            .await
+            .catch_status("MpcSessionManager::Inbox")?;
+        if reqs.is_empty() {
+            return Ok(());
+        }
+
+        let mut req = authorized(VecMessage { values: reqs }, &self.auth);
+        req.set_timeout(self.deadline.saturating_duration_since(Instant::now()));
+        let peer_echoes = self
+            .cl
+            .outbox(req)
+            .await
+            .catch_status("MpcSessionManager::Outbox")?
+            .into_inner()
+            .values;
+        for mut echo in peer_echoes.into_iter() {
+            let (round, peer) = rounds
+                .iter()
+                .find_map(|round| Some((round, round.peer_of(&echo)?)))
+                .ifnone("", "Echo not requested")?;
+            if let Some(sig) = self.sig.as_ref() {
+                sig.verify_from(&mut echo, peer).catch_()?;
+            }
+            round.check(&echo, peer).catch_()?;
+        }
+        Ok(())
+    }
+
+    fn index_requests(&self) -> Vec<Message> {
+        self.rx
+            .keys()
+            .map(|idx| Message {
+                session_id: self.sid.clone(),
+                topic: idx.topic.clone(),
+                src: idx.src as u64,
+                dst: idx.dst as u64,
+                seq: idx.seq as u64,
+                obj: None,
+            })
+            .collect()
+    }
+
+    fn accept_received(&mut self, msgs: Vec<Message>) -> Resultat<()> {
+        let mut key_set: HashSet<MessageIndex> = self
+            .rx
+            .iter()
+            .filter(|(_, obj)| obj.is_none())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for msg in msgs.into_iter() {
+            let key = MessageIndex {
+                topic: msg.topic,
+                src: msg.src as usize,
+                dst: msg.dst as usize,
+                seq: msg.seq as usize,
+            };
+            assert_throw!(key_set.contains(&key), "Message not registered");
+            let obj = msg.obj.ifnone("", "Unexpected null message")?;
+            key_set.remove(&key);
+            self.rx.insert(key, Some(obj)); // update
+        }
+        Ok(())
+    }
 }
 
 #[tonic::async_trait]
@@ -146,14 +781,53 @@ impl BatchMessenger for SvarogChannel {
     }
 
     async fn execute_send(&mut self) -> Resultat<()> {
+        let mut msgs: Vec<Message> = self.tx.drain(..).collect();
+        self.seal(&mut msgs).await.catch_()?;
+        if let Some(echo) = self.echo.as_mut() {
+            echo.note_sent(&msgs).catch_()?;
+        }
+        if let Some(sig) = self.sig.as_ref() {
+            for msg in msgs.iter_mut() {
+                sig.sign(msg).catch_()?;
+            }
+        }
+        for msg in msgs.iter() {
+            debug!(
+                topic = %msg.topic,
+                src = msg.src,
+                dst = msg.dst,
+                seq = msg.seq,
+                size = msg.obj.as_ref().map_or(0, |obj| obj.len()),
+                "send"
+            );
+        }
+        let deadline = self.deadline;
+        if let Some(ex) = self.exchange().await.catch_()? {
+            let n = msgs.len();
+            for msg in msgs.into_iter() {
+                ex.tx
+                    .send(msg)
+                    .await
+                    .catch("GrpcCallFailed", "MpcSessionManager::Exchange")?;
+            }
+            // Done once every message is stored, as with `Inbox`. Every ack is
+            // taken before failing, so that none is left for `execute_receive`.
+            let mut acks = Vec::with_capacity(n);
+            for _ in 0..n {
+                acks.push(ex.reply(deadline).await);
+            }
+            for ack in acks {
+                ack.catch_()?;
+            }
+            return Ok(());
+        }
+
         let cl = &mut self.cl;
-        let req = VecMessage {
-            values: self.tx.drain(..).collect(),
-        };
+        let req = authorized(VecMessage { values: msgs }, &self.auth);
         let _ = cl
             .inbox(req)
             .await
-            .catch("GrpcCallFailed", "MpcSessionManager::Inbox")?;
+            .catch_status("MpcSessionManager::Inbox")?;
         Ok(())
     }
 
@@ -179,41 +853,54 @@ impl BatchMessenger for SvarogChannel {
     }
 
     async fn execute_receive(&mut self) -> Resultat<()> {
-        let cl = &mut self.cl;
-        let req = self
-            .rx
-            .iter()
-            .map(|(idx, _)| Message {
-                session_id: self.sid.clone(),
-                topic: idx.topic.clone(),
-                src: idx.src as u64,
-                dst: idx.dst as u64,
-                seq: idx.seq as u64,
-                obj: None,
-            })
-            .collect();
-        let mut req = Request::new(VecMessage { values: req });
-        req.set_timeout(Duration::from_millis(SESSION_EXPIRE_MS as u64));
-        let resp = cl
-            .outbox(req)
-            .await
-            .catch("GrpcCallFailed", "MpcSessionManager::Outbox")?
-            .into_inner();
+        let req = self.index_requests();
+        let n = req.len();
+        debug!(n, "wait for messages");
+        let deadline = self.deadline;
 
-        let mut key_set: HashSet<MessageIndex> = self.rx.keys().cloned().collect();
-        for msg in resp.values.iter() {
-            let key = MessageIndex {
-                topic: msg.topic.clone(),
-                src: msg.src as usize,
-                dst: msg.dst as usize,
-                seq: msg.seq as usize,
-            };
-            assert_throw!(key_set.contains(&key), "Message not registered");
-            let obj = msg.obj.clone().ifnone("", "Unexpected null message")?;
-            key_set.remove(&key);
-            self.rx.insert(key, Some(obj)); // update
+        let mut resp = if let Some(ex) = self.exchange().await.catch_()? {
+            for idx in req.into_iter() {
+                ex.tx
+                    .send(idx)
+                    .await
+                    .catch("GrpcCallFailed", "MpcSessionManager::Exchange")?;
+            }
+            // Every reply is taken before failing, so that none is left for
+            // the next round.
+            let mut replies = Vec::with_capacity(n);
+            for _ in 0..n {
+                replies.push(ex.reply(deadline).await);
+            }
+            let mut resp = Vec::with_capacity(n);
+            for reply in replies {
+                resp.push(reply.catch_()?);
+            }
+            resp
+        } else {
+            let cl = &mut self.cl;
+            let mut req = authorized(VecMessage { values: req }, &self.auth);
+            req.set_timeout(deadline.saturating_duration_since(Instant::now()));
+            cl.outbox(req)
+                .await
+                .catch_status("MpcSessionManager::Outbox")?
+                .into_inner()
+                .values
+        };
+
+        debug!(n = resp.len(), "received");
+        if let Some(sig) = self.sig.as_ref() {
+            for msg in resp.iter_mut() {
+                sig.verify(msg).catch_()?;
+            }
         }
-        assert_throw!(key_set.is_empty(), "Some messages are missing");
+        let rounds = match self.echo.as_ref() {
+            Some(echo) => echo.rounds(&resp).catch_()?,
+            None => Vec::new(),
+        };
+        self.accept_received(resp).catch_()?;
+        let missing = self.rx.values().any(|obj| obj.is_none());
+        assert_throw!(!missing, "Some messages are missing");
+        self.echo(rounds).await.catch_()?;
 
         Ok(())
     }
@@ -241,7 +928,21 @@ impl BatchMessenger for SvarogChannel {
             .as_ref()
             .ifnone("", "Unexpected null message")?;
 
-        let obj = serde_pickle::from_slice(val, Default::default()).catch_()?;
+        let obj = match self.e2e.as_ref() {
+            Some(e2e) if dst != 0 => {
+                let msg = Message {
+                    session_id: self.sid.clone(),
+                    topic: topic.to_owned(),
+                    src: src as u64,
+                    dst: dst as u64,
+                    seq: seq as u64,
+                    obj: Some(val.clone()),
+                };
+                let val = e2e.open(&msg).catch_()?;
+                serde_pickle::from_slice(&val, Default::default()).catch_()?
+            }
+            _ => serde_pickle::from_slice(val, Default::default()).catch_()?,
+        };
         Ok(obj)
     }
 
@@ -249,3 +950,41 @@ impl BatchMessenger for SvarogChannel {
         self.rx.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use erreur::*;
+    use svarog_grpc::SessionConfig;
+
+    use crate::*;
+
+    #[test]
+    fn test_name_indices() -> Resultat<()> {
+        let names = |players: &[(&str, bool)]| {
+            players
+                .iter()
+                .map(|&(name, att)| (name.to_owned(), att))
+                .collect()
+        };
+        let cfg = SessionConfig {
+            players: names(&[("A", true), ("B", false), ("C", true)]),
+            players_reshared: names(&[("C", true), ("D", true)]),
+            ..Default::default()
+        };
+        let expected: BTreeMap<String, BTreeSet<u64>> = [
+            ("", vec![0]),
+            ("A", vec![1]),
+            ("C", vec![1, 3]),
+            ("D", vec![2]),
+        ]
+        .into_iter()
+        .map(|(name, indices)| (name.to_owned(), indices.into_iter().collect()))
+        .collect();
+        assert_throw!(name_indices(&cfg) == expected);
+        // Only the provider sends as index 0.
+        assert_throw!(index_holders(&cfg)[&0] == vec!["".to_owned()]);
+        Ok(())
+    }
+}
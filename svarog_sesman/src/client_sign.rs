@@ -0,0 +1,269 @@
+//! Signatures of the senders, for sessions with `SessionConfig.player_keys`.
+//!
+//! Every message is signed with the Ed25519 identity key of its sender over
+//! `(session_id, topic, src, dst, seq, obj)`, and the payload is wrapped along with
+//! the signature. A receiver accepts the message only if the signature verifies
+//! against the key pinned for a party holding `src`, so sesman cannot forge or alter it.
+
+use std::collections::{BTreeMap, HashMap};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use erreur::*;
+use serde::{Deserialize, Serialize};
+use svarog_grpc::{Message, SessionConfig};
+
+use crate::{index_holders, ERR_FORGED_MESSAGE};
+
+/// Hex of the 32-byte Ed25519 seed of the player, read by `SvarogChannel::use_session`
+/// if the session pins identity keys.
+pub const IDENTITY_KEY_FILE: &str = "tls/identity.key";
+
+#[derive(Serialize, Deserialize)]
+struct Signed {
+    #[serde(with = "serde_bytes")]
+    sig: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    obj: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Signatures {
+    /// Signs the messages sent. Without it, the channel can only receive.
+    identity: Option<SigningKey>,
+    /// Parties holding each index, who may send messages as it.
+    holders: BTreeMap<u64, Vec<String>>,
+    /// The pinned keys, by player name.
+    keys: HashMap<String, VerifyingKey>,
+}
+
+fn signed_bytes(msg: &Message, obj: &[u8]) -> Resultat<Vec<u8>> {
+    let signed = (
+        &msg.session_id,
+        &msg.topic,
+        msg.src,
+        msg.dst,
+        msg.seq,
+        serde_bytes::Bytes::new(obj),
+    );
+    serde_pickle::to_vec(&signed, Default::default()).catch_()
+}
+
+pub(crate) fn parse_identity(seed_hex: &str) -> Resultat<SigningKey> {
+    let seed: [u8; 32] = hex::decode(seed_hex.trim())
+        .ok()
+        .and_then(|seed| seed.try_into().ok())
+        .ifnone(
+            "",
+            "Identity key should be the hex of a 32-byte Ed25519 seed",
+        )?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// The identity key in `IDENTITY_KEY_FILE`, if the file exists.
+pub(crate) async fn read_identity() -> Resultat<Option<SigningKey>> {
+    if !tokio::fs::try_exists(IDENTITY_KEY_FILE).await.catch_()? {
+        return Ok(None);
+    }
+    let seed_hex = tokio::fs::read_to_string(IDENTITY_KEY_FILE)
+        .await
+        .catch("", format!("Try reading {}", IDENTITY_KEY_FILE))?;
+    let identity = parse_identity(&seed_hex).catch_()?;
+    Ok(Some(identity))
+}
+
+impl Signatures {
+    /// Fail if `identity` is not the one pinned for `player`.
+    pub fn new(cfg: &SessionConfig, player: &str, identity: Option<SigningKey>) -> Resultat<Self> {
+        let mut keys = HashMap::new();
+        for (name, key_hex) in cfg.player_keys.iter() {
+            let key: [u8; 32] = hex::decode(key_hex)
+                .ok()
+                .and_then(|key| key.try_into().ok())
+                .ifnone("", format!("Malformed identity key of player {:?}", name))?;
+            let key = VerifyingKey::from_bytes(&key)
+                .catch("", format!("Invalid identity key of player {:?}", name))?;
+            keys.insert(name.clone(), key);
+        }
+        if let Some(identity) = identity.as_ref() {
+            assert_throw!(
+                keys.get(player) == Some(&identity.verifying_key()),
+                format!(
+                    "The identity key is not the one pinned for player {:?}",
+                    player
+                )
+            );
+        }
+        Ok(Self {
+            identity,
+            holders: index_holders(cfg),
+            keys,
+        })
+    }
+
+    /// Wrap the payload along with its signature.
+    pub fn sign(&self, msg: &mut Message) -> Resultat<()> {
+        let identity = self.identity.as_ref().ifnone(
+            "",
+            format!(
+                "The session pins identity keys, but neither {} nor an identity is given",
+                IDENTITY_KEY_FILE
+            ),
+        )?;
+        let obj = msg.obj.take().ifnone("", "Unexpected null message")?;
+        let sig = identity.sign(&signed_bytes(msg, &obj).catch_()?);
+        let signed = Signed {
+            sig: sig.to_bytes().to_vec(),
+            obj,
+        };
+        msg.obj = Some(serde_pickle::to_vec(&signed, Default::default()).catch_()?);
+        Ok(())
+    }
+
+    /// Unwrap the payload, if signed by a party holding `src`.
+    pub fn verify(&self, msg: &mut Message) -> Resultat<()> {
+        let names = self.holders.get(&msg.src).cloned().unwrap_or_default();
+        self.verify_as(msg, &names)
+    }
+
+    /// Unwrap the payload, if signed by the named party.
+    pub fn verify_from(&self, msg: &mut Message, name: &str) -> Resultat<()> {
+        self.verify_as(msg, &[name.to_owned()])
+    }
+
+    fn verify_as(&self, msg: &mut Message, names: &[String]) -> Resultat<()> {
+        let forged = format!(
+            "Message {}-{}-{}-{} claimed from {:?} is not signed by them",
+            &msg.topic, msg.src, msg.dst, msg.seq, names
+        );
+        let obj = msg.obj.as_ref().ifnone("", "Unexpected null message")?;
+        let signed: Signed =
+            serde_pickle::from_slice(obj, Default::default()).catch(ERR_FORGED_MESSAGE, &forged)?;
+        let sig = Signature::from_slice(&signed.sig).catch(ERR_FORGED_MESSAGE, &forged)?;
+        let bytes = signed_bytes(msg, &signed.obj).catch_()?;
+        names
+            .iter()
+            .find(|name| {
+                self.keys
+                    .get(*name)
+                    .is_some_and(|key| key.verify(&bytes, &sig).is_ok())
+            })
+            .ifnone(ERR_FORGED_MESSAGE, &forged)?;
+        msg.obj = Some(signed.obj);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SigningKey;
+    use erreur::*;
+    use svarog_grpc::{Message, SessionConfig};
+
+    use super::Signatures;
+    use crate::is_message_forged;
+
+    fn identity(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    /// A and B are pinned to the identities of seeds 1 and 2, C to none.
+    fn config() -> SessionConfig {
+        SessionConfig {
+            session_id: "sid".to_owned(),
+            players: [("A", true), ("B", true), ("C", true)]
+                .into_iter()
+                .map(|(name, att)| (name.to_owned(), att))
+                .collect(),
+            player_keys: [("A", 1u8), ("B", 2)]
+                .into_iter()
+                .map(|(name, seed)| {
+                    let key = identity(seed).verifying_key();
+                    (name.to_owned(), hex::encode(key.as_bytes()))
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// A message from A, as index 1, to B, as index 2.
+    fn message() -> Message {
+        Message {
+            session_id: "sid".to_owned(),
+            topic: "topic".to_owned(),
+            src: 1,
+            dst: 2,
+            seq: 0,
+            obj: Some(b"payload".to_vec()),
+        }
+    }
+
+    fn forged(sig: &Signatures, mut msg: Message) -> bool {
+        sig.verify(&mut msg)
+            .err()
+            .is_some_and(|e| is_message_forged(&e))
+    }
+
+    #[test]
+    fn test_sign_verify() -> Resultat<()> {
+        let cfg = config();
+        let a = Signatures::new(&cfg, "A", Some(identity(1))).catch_()?;
+        let b = Signatures::new(&cfg, "B", Some(identity(2))).catch_()?;
+        let mut msg = message();
+        a.sign(&mut msg).catch_()?;
+        let mut received = msg.clone();
+        b.verify(&mut received).catch_()?;
+        assert_throw!(received == message());
+        b.verify_from(&mut msg, "A").catch_()?;
+        assert_throw!(msg == message());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_rejects() -> Resultat<()> {
+        let cfg = config();
+        let a = Signatures::new(&cfg, "A", Some(identity(1))).catch_()?;
+        let b = Signatures::new(&cfg, "B", Some(identity(2))).catch_()?;
+        let mut msg = message();
+        a.sign(&mut msg).catch_()?;
+
+        let mut changed = msg.clone();
+        changed.src = 3;
+        assert_throw!(forged(&b, changed));
+        let mut changed = msg.clone();
+        changed.seq = 1;
+        assert_throw!(forged(&b, changed));
+        assert_throw!(forged(
+            &b,
+            Message {
+                obj: Some(b"not signed".to_vec()),
+                ..message()
+            }
+        ));
+
+        // Signed by B, or with a key other than the pinned one, but claimed from A.
+        let mut by_b = message();
+        b.sign(&mut by_b).catch_()?;
+        assert_throw!(forged(&b, by_b.clone()));
+        assert_throw!(b.verify_from(&mut by_b, "A").is_err());
+        let mut other = config();
+        let key = identity(3).verifying_key();
+        other
+            .player_keys
+            .insert("A".to_owned(), hex::encode(key.as_bytes()));
+        let impostor = Signatures::new(&other, "A", Some(identity(3))).catch_()?;
+        let mut by_impostor = message();
+        impostor.sign(&mut by_impostor).catch_()?;
+        assert_throw!(forged(&b, by_impostor));
+        Ok(())
+    }
+
+    #[test]
+    fn test_identity_is_pinned() -> Resultat<()> {
+        let cfg = config();
+        assert_throw!(Signatures::new(&cfg, "A", Some(identity(2))).is_err());
+        // A channel without an identity can only receive.
+        let a = Signatures::new(&cfg, "A", None).catch_()?;
+        assert_throw!(a.sign(&mut message()).is_err());
+        Ok(())
+    }
+}
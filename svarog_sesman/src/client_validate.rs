@@ -0,0 +1,269 @@
+//! Checks of `SessionConfig` per `SessionKind`, run by sesman in `NewSession` and by
+//! `svarog_peer` before joining, so that an unfit config fails at once with a precise
+//! message, instead of deep inside the protocol or as a hang.
+
+use std::collections::HashMap;
+
+use erreur::*;
+use svarog_grpc::{SessionConfig, SessionKind};
+
+use crate::player_indices;
+
+/// Title of the error raised when a config is unfit for the kind of session.
+pub const ERR_INVALID_SESSION_CONFIG: &str = "InvalidSessionConfig";
+
+fn check(ok: bool, msg: String) -> Resultat<()> {
+    ok.then_some(()).ifnone(ERR_INVALID_SESSION_CONFIG, msg)
+}
+
+fn check_threshold(t: u64, n: usize, of: &str) -> Resultat<()> {
+    check(
+        0 < t && t < n as u64,
+        format!(
+            "threshold {} should be positive and less than the {} {}",
+            t, n, of
+        ),
+    )
+}
+
+/// Whether two URLs of sesman are the same, regardless of a trailing slash.
+pub fn same_sesman_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+/// Check the config for a session of `kind`. The config may leave its own kind
+/// unspecified, otherwise it should be `kind`.
+pub fn validate_session_config(cfg: &SessionConfig, kind: SessionKind) -> Resultat<()> {
+    let declared = SessionKind::try_from(cfg.kind).ok();
+    check(
+        declared.is_some(),
+        format!("Unknown session kind {}", cfg.kind),
+    )?;
+    let declared = declared.unwrap_or(SessionKind::Unspecified);
+    check(
+        [SessionKind::Unspecified, kind].contains(&declared),
+        format!(
+            "Session is for {}, not for {}",
+            declared.as_str_name(),
+            kind.as_str_name()
+        ),
+    )?;
+    let url = &cfg.sesman_url;
+    check(
+        url.is_empty() || url.starts_with("http://") || url.starts_with("https://"),
+        format!("sesman_url {:?} should start with http:// or https://", url),
+    )?;
+    check(!cfg.players.is_empty(), "players is empty".to_owned())?;
+    for (field, players) in [
+        ("players", &cfg.players),
+        ("players_reshared", &cfg.players_reshared),
+    ] {
+        check(
+            !players.contains_key(""),
+            format!(
+                "{} has an empty name, which stands for the party outside of the players",
+                field
+            ),
+        )?;
+    }
+    check(
+        !cfg.e2e || !cfg.player_keys.is_empty(),
+        "e2e needs player_keys, to authenticate the keys of the parties".to_owned(),
+    )?;
+    let attending = player_indices(&cfg.players).len();
+    check(attending > 0, "No player attends".to_owned())?;
+
+    let all_attend = |field: &str, players: &HashMap<String, bool>| {
+        let absent: Vec<&String> = players
+            .iter()
+            .filter_map(|(name, &att)| (!att).then_some(name))
+            .collect();
+        check(
+            absent.is_empty(),
+            format!(
+                "Every player in {} should attend a {} session, but {:?} do not",
+                field,
+                kind.as_str_name(),
+                absent
+            ),
+        )
+    };
+    let no_reshare = || {
+        check(
+            cfg.players_reshared.is_empty(),
+            format!(
+                "players_reshared is only for RESHARE, not for {}",
+                kind.as_str_name()
+            ),
+        )
+    };
+    match kind {
+        SessionKind::Keygen | SessionKind::KeygenMnem => {
+            all_attend("players", &cfg.players)?;
+            no_reshare()?;
+            check_threshold(cfg.threshold, cfg.players.len(), "players")?;
+        }
+        SessionKind::Sign => no_reshare()?,
+        SessionKind::Reshare => {
+            check(
+                !cfg.players_reshared.is_empty(),
+                "RESHARE needs players_reshared, the players to reshare to".to_owned(),
+            )?;
+            all_attend("players_reshared", &cfg.players_reshared)?;
+            check_threshold(
+                cfg.threshold,
+                cfg.players_reshared.len(),
+                "players_reshared",
+            )?;
+        }
+        SessionKind::Unspecified => {
+            // Only the bounds that hold for every kind of session.
+            let (n, of) = match cfg.players_reshared.len() {
+                0 => (cfg.players.len(), "players"),
+                n => (n, "players_reshared"),
+            };
+            check(
+                0 < cfg.threshold && cfg.threshold <= n as u64,
+                format!(
+                    "threshold {} should be positive and at most the {} {}",
+                    cfg.threshold, n, of
+                ),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use svarog_grpc::{SessionConfig, SessionKind};
+
+    use super::validate_session_config;
+    use crate::is_session_config_invalid;
+
+    fn config(
+        players: &[(&str, bool)],
+        reshared: &[(&str, bool)],
+        threshold: u64,
+    ) -> SessionConfig {
+        let names = |players: &[(&str, bool)]| {
+            players
+                .iter()
+                .map(|&(name, att)| (name.to_owned(), att))
+                .collect()
+        };
+        SessionConfig {
+            players: names(players),
+            players_reshared: names(reshared),
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    fn invalid(cfg: &SessionConfig, kind: SessionKind) -> bool {
+        validate_session_config(cfg, kind)
+            .err()
+            .is_some_and(|e| is_session_config_invalid(&e))
+    }
+
+    #[test]
+    fn test_keygen() -> Resultat<()> {
+        let all = [("A", true), ("B", true), ("C", true)];
+        for kind in [SessionKind::Keygen, SessionKind::KeygenMnem] {
+            validate_session_config(&config(&all, &[], 1), kind).catch_()?;
+            validate_session_config(&config(&all, &[], 2), kind).catch_()?;
+            assert_throw!(invalid(&config(&all, &[], 0), kind));
+            assert_throw!(invalid(&config(&all, &[], 3), kind));
+            assert_throw!(invalid(&config(&[("A", true), ("B", false)], &[], 1), kind));
+            assert_throw!(invalid(&config(&all, &[("D", true)], 1), kind));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign() -> Resultat<()> {
+        let kind = SessionKind::Sign;
+        validate_session_config(&config(&[("A", true), ("B", false)], &[], 1), kind).catch_()?;
+        assert_throw!(invalid(
+            &config(&[("A", false), ("B", false)], &[], 1),
+            kind
+        ));
+        assert_throw!(invalid(&config(&[("A", true)], &[("D", true)], 1), kind));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reshare() -> Resultat<()> {
+        let kind = SessionKind::Reshare;
+        let old = [("A", true), ("B", false)];
+        let new = [("C", true), ("D", true), ("E", true)];
+        validate_session_config(&config(&old, &new, 2), kind).catch_()?;
+        assert_throw!(invalid(&config(&old, &[], 1), kind));
+        assert_throw!(invalid(&config(&old, &new, 3), kind));
+        assert_throw!(invalid(
+            &config(&old, &[("C", true), ("D", false)], 1),
+            kind
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_common() -> Resultat<()> {
+        let kind = SessionKind::Sign;
+        let cfg = config(&[("A", true), ("B", true)], &[], 1);
+        assert_throw!(invalid(&config(&[], &[], 0), kind));
+        assert_throw!(invalid(&config(&[("", true), ("A", true)], &[], 1), kind));
+
+        let declared = |declared: i32| SessionConfig {
+            kind: declared,
+            ..cfg.clone()
+        };
+        validate_session_config(&declared(SessionKind::Sign as i32), kind).catch_()?;
+        assert_throw!(invalid(&declared(SessionKind::Keygen as i32), kind));
+        assert_throw!(invalid(&declared(99), kind));
+
+        for (url, ok) in [
+            ("", true),
+            ("http://sesman:2000", true),
+            ("https://sesman", true),
+            ("sesman:2000", false),
+        ] {
+            let with_url = SessionConfig {
+                sesman_url: url.to_owned(),
+                ..cfg.clone()
+            };
+            assert_throw!(invalid(&with_url, kind) != ok, url);
+        }
+
+        let e2e = SessionConfig {
+            e2e: true,
+            ..cfg.clone()
+        };
+        assert_throw!(invalid(&e2e, kind));
+        let pinned = SessionConfig {
+            player_keys: [("A", "aa"), ("B", "bb")]
+                .into_iter()
+                .map(|(name, key)| (name.to_owned(), key.to_owned()))
+                .collect(),
+            ..e2e
+        };
+        validate_session_config(&pinned, kind).catch_()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unspecified() -> Resultat<()> {
+        let kind = SessionKind::Unspecified;
+        let players = [("A", true), ("B", true)];
+        validate_session_config(&config(&players, &[], 1), kind).catch_()?;
+        validate_session_config(&config(&players, &[], 2), kind).catch_()?;
+        assert_throw!(invalid(&config(&players, &[], 0), kind));
+        assert_throw!(invalid(&config(&players, &[], 3), kind));
+        // Checked against players_reshared if any.
+        let new = [("C", true), ("D", true), ("E", true)];
+        validate_session_config(&config(&players, &new, 3), kind).catch_()?;
+        assert_throw!(invalid(&config(&players, &new, 4), kind));
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Operational RPCs of sesman, served beside `MpcSessionManager`
+//! but guarded by a credential of their own.
+
+use erreur::*;
+use svarog_grpc::{
+    sesman_admin_server::SesmanAdmin, SessionId, SessionSummary, Usage, VecSessionSummary, Void,
+};
+use tonic::{Request, Response, Status};
+use tracing::{field::Empty, instrument, Level, Span};
+
+use crate::{server_impl::bearer_token, server_session::token_digest, Sesman};
+
+pub struct Admin {
+    sesman: Sesman,
+    /// Digest of the admin token, so that tokens are compared in fixed length.
+    token_digest: String,
+}
+
+impl Admin {
+    pub fn new(sesman: Sesman, token: &str) -> Resultat<Self> {
+        assert_throw!(!token.is_empty(), "Admin token should not be empty");
+        Ok(Self {
+            sesman,
+            token_digest: token_digest(token).catch_()?,
+        })
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(&self, req: &Request<T>) -> Result<(), Status> {
+        let token = bearer_token(req.metadata())?;
+        let digest = token_digest(&token).map_err(|e| Status::internal(e.to_string()))?;
+        if digest != self.token_digest {
+            return Err(Status::unauthenticated("Invalid admin token"));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl SesmanAdmin for Admin {
+    #[instrument(
+        skip_all,
+        fields(op = "ListSessions", session_id = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn list_sessions(
+        &self,
+        request: Request<Void>,
+    ) -> Result<Response<VecSessionSummary>, Status> {
+        self.authorize(&request)?;
+        let values = self.sesman.session_summaries()?;
+        Ok(Response::new(VecSessionSummary { values }))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "InspectSession", session_id = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn inspect_session(
+        &self,
+        request: Request<SessionId>,
+    ) -> Result<Response<SessionSummary>, Status> {
+        self.authorize(&request)?;
+        let sid = request.into_inner().value;
+        Span::current().record("session_id", sid.as_str());
+        let summary = self.sesman.session_summary(&sid)?;
+        Ok(Response::new(summary))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "PurgeSession", session_id = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn purge_session(&self, request: Request<SessionId>) -> Result<Response<Void>, Status> {
+        self.authorize(&request)?;
+        let sid = request.into_inner().value;
+        Span::current().record("session_id", sid.as_str());
+        self.sesman.purge_session(&sid)?;
+        Ok(Response::new(Void {}))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "GetUsage", session_id = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn get_usage(&self, request: Request<Void>) -> Result<Response<Usage>, Status> {
+        self.authorize(&request)?;
+        Ok(Response::new(self.sesman.usage()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use svarog_grpc::{
+        mpc_session_manager_server::MpcSessionManager, sesman_admin_server::SesmanAdmin, Message,
+        SessionId, SessionState, VecMessage, Void,
+    };
+    use tokio::time::{sleep, timeout, Duration};
+    use tonic::{Code, Request};
+
+    use super::Admin;
+    use crate::server_impl::tests::{players, request, sesman, settings};
+
+    #[tokio::test]
+    async fn test_get_usage_needs_admin_token() -> Resultat<()> {
+        let admin = Admin::new(sesman(settings()).await?, "admin-token")?;
+        let res = admin.get_usage(Request::new(Void {})).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::Unauthenticated));
+        let res = admin.get_usage(request(Void {}, "other-token")).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::Unauthenticated));
+        let usage = admin
+            .get_usage(request(Void {}, "admin-token"))
+            .await
+            .catch_()?
+            .into_inner();
+        assert_throw!(usage.sessions == 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_inspect_purge() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let admin = Admin::new(sesman.clone(), "admin-token")?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let session_id = || SessionId {
+            value: sid.value.clone(),
+            ..Default::default()
+        };
+        let res = admin
+            .inspect_session(request(session_id(), &sid.tokens["A"]))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::Unauthenticated));
+        let res = admin.purge_session(Request::new(session_id())).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::Unauthenticated));
+
+        let msg = Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst: 0,
+            seq: 0,
+            obj: Some(b"xyz".to_vec()),
+        };
+        sesman
+            .inbox(request(
+                VecMessage {
+                    values: vec![msg.clone()],
+                },
+                &sid.tokens["A"],
+            ))
+            .await
+            .catch_()?;
+        let idx = Message {
+            seq: 1,
+            obj: None,
+            ..msg
+        };
+        let waiter = {
+            let sesman = sesman.clone();
+            let req = request(VecMessage { values: vec![idx] }, &sid.tokens["B"]);
+            tokio::spawn(async move { sesman.outbox(req).await })
+        };
+        sleep(Duration::from_millis(100)).await;
+
+        let list = admin
+            .list_sessions(request(Void {}, "admin-token"))
+            .await
+            .catch_()?
+            .into_inner()
+            .values;
+        assert_throw!(list.len() == 1);
+        let summary = admin
+            .inspect_session(request(session_id(), "admin-token"))
+            .await
+            .catch_()?
+            .into_inner();
+        assert_throw!(summary.state() == SessionState::Running);
+        assert_throw!((summary.messages, summary.bytes, summary.waiting) == (1, 3, 1));
+        assert_throw!((summary.joined, summary.completed, summary.participants) == (2, 0, 2));
+        let cfg = summary.config.ifnone_()?;
+        assert_throw!(cfg.session_id == sid.value && cfg.ttl_remaining > 0);
+
+        admin
+            .purge_session(request(session_id(), "admin-token"))
+            .await
+            .catch_()?;
+        let res = timeout(Duration::from_millis(300), waiter)
+            .await
+            .catch("", "Outbox not woken up by the purge")?
+            .catch_()?;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::NotFound));
+        let res = admin
+            .inspect_session(request(session_id(), "admin-token"))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::NotFound));
+        let usage = sesman.usage();
+        assert_throw!((usage.sessions, usage.messages, usage.bytes) == (0, 0, 0));
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Writer of the audit log, see `svarog_sesman::AuditRecord` for the format.
+//!
+//! Records are appended and synced one at a time, continuing the chain of an existing log,
+//! by a thread of their own, so that no request waits on the disk while holding a lock
+//! or a worker of the runtime.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    sync::mpsc,
+};
+
+use erreur::*;
+use serde_json::Value;
+use svarog_sesman::{last_audit_record, AuditRecord, AUDIT_GENESIS};
+use tokio::sync::oneshot;
+use tracing::{error, info};
+
+use crate::server_session::now_ms;
+
+struct Chain {
+    file: File,
+    seq: u64,
+    prev: String,
+}
+
+impl Chain {
+    fn append(&mut self, mut rec: AuditRecord) -> Resultat<()> {
+        rec.seq = self.seq;
+        rec.prev = self.prev.clone();
+        let (line, hash) = rec.to_line().catch_()?;
+        self.file
+            .write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .catch(
+                "",
+                format!(
+                    "Try appending {} of session {} to audit log",
+                    &rec.event, &rec.session_id
+                ),
+            )?;
+        self.seq += 1;
+        self.prev = hash;
+        Ok(())
+    }
+}
+
+struct Job {
+    /// Chained by the writer. None only to flush.
+    rec: Option<AuditRecord>,
+    /// Told once the record is synced, unless nobody waits for it.
+    done: Option<oneshot::Sender<Resultat<()>>>,
+}
+
+/// Writes nothing unless opened with a path.
+#[derive(Default)]
+pub struct AuditLog {
+    jobs: Option<mpsc::Sender<Job>>,
+}
+
+impl AuditLog {
+    pub fn open(path: &str) -> Resultat<Self> {
+        let last = if std::path::Path::new(path).exists() {
+            last_audit_record(path).catch(
+                "",
+                format!("Audit log {} is broken, check it with svarog_audit", path),
+            )?
+        } else {
+            None
+        };
+        let (seq, prev) = match last {
+            Some((rec, hash)) => (rec.seq + 1, hash),
+            None => (0, AUDIT_GENESIS.to_owned()),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .catch("", format!("Try opening audit log {}", path))?;
+        info!(
+            records = seq,
+            "svarog_sesman will append to audit log {}", path
+        );
+        let mut chain = Chain { file, seq, prev };
+        let (jobs, queue) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("audit".to_owned())
+            .spawn(move || {
+                for job in queue {
+                    let res = match job.rec {
+                        Some(rec) => chain.append(rec),
+                        None => Ok(()),
+                    };
+                    match job.done {
+                        Some(done) => {
+                            let _ = done.send(res);
+                        }
+                        None => {
+                            if let Err(e) = res {
+                                error!("Failed to audit: {}", e);
+                            }
+                        }
+                    }
+                }
+            })
+            .catch("", "Try starting the writer of audit log")?;
+        Ok(Self { jobs: Some(jobs) })
+    }
+
+    fn queue(
+        &self,
+        rec: Option<AuditRecord>,
+        done: Option<oneshot::Sender<Resultat<()>>>,
+    ) -> Resultat<()> {
+        let Some(jobs) = self.jobs.as_ref() else {
+            return Ok(());
+        };
+        jobs.send(Job { rec, done })
+            .catch("", "Writer of audit log is gone")
+    }
+
+    /// Append a record to the chain, after those queued before it.
+    pub async fn record(
+        &self,
+        event: &str,
+        session_id: &str,
+        player: &str,
+        detail: Value,
+    ) -> Resultat<()> {
+        if self.jobs.is_none() {
+            return Ok(());
+        }
+        let (done, synced) = oneshot::channel();
+        self.queue(
+            Some(new_record(event, session_id, player, detail)),
+            Some(done),
+        )?;
+        synced.await.catch("", "Writer of audit log is gone")?
+    }
+
+    /// Like `record`, without waiting, for events that happen while a request fails
+    /// anyway, or outside of requests. Failures are logged.
+    pub fn record_or_log(&self, event: &str, session_id: &str, player: &str, detail: Value) {
+        let rec = new_record(event, session_id, player, detail);
+        if let Err(e) = self.queue(Some(rec), None) {
+            error!("Failed to audit {} of session {}: {}", event, session_id, e);
+        }
+    }
+
+    /// Wait until the records queued so far are synced.
+    pub async fn flush(&self) -> Resultat<()> {
+        if self.jobs.is_none() {
+            return Ok(());
+        }
+        let (done, flushed) = oneshot::channel();
+        self.queue(None, Some(done))?;
+        flushed.await.catch("", "Writer of audit log is gone")?
+    }
+}
+
+/// A record yet to be chained.
+fn new_record(event: &str, session_id: &str, player: &str, detail: Value) -> AuditRecord {
+    AuditRecord {
+        seq: 0,
+        time: now_ms(),
+        event: event.to_owned(),
+        session_id: session_id.to_owned(),
+        player: player.to_owned(),
+        detail,
+        prev: String::new(),
+    }
+}
@@ -0,0 +1,112 @@
+//! Client certificates of mTLS.
+
+use erreur::*;
+use sha2::{Digest, Sha256};
+use svarog_grpc::CertBinding;
+use tonic::Request;
+use x509_parser::parse_x509_certificate;
+
+use crate::server_rest::RestPeerCert;
+
+/// What sesman knows about a client from its certificate.
+#[derive(Clone, Debug)]
+pub struct PeerCert {
+    pub subject: String,
+    pub spki_sha256: String,
+}
+
+impl PeerCert {
+    pub fn from_der(der: &[u8]) -> Resultat<Self> {
+        let (_, cert) = parse_x509_certificate(der).catch("", "Malformed client certificate")?;
+        let subject = cert.subject().to_string();
+        let spki_sha256 = hex::encode(Sha256::digest(cert.public_key().raw));
+        Ok(Self {
+            subject,
+            spki_sha256,
+        })
+    }
+
+    /// The leaf certificate that the client presented, if any,
+    /// over gRPC or the REST gateway.
+    pub fn of_request<T>(req: &Request<T>) -> Resultat<Option<Self>> {
+        if let Some(certs) = req.peer_certs() {
+            return match certs.first() {
+                Some(leaf) => Ok(Some(Self::from_der(leaf.get_ref()).catch_()?)),
+                None => Ok(None),
+            };
+        }
+        match req.extensions().get::<RestPeerCert>() {
+            Some(cert) => Ok(Some(Self::from_der(&cert.0).catch_()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// An empty binding matches no certificate.
+    pub fn matches(&self, binding: &CertBinding) -> bool {
+        if binding.subject.is_empty() && binding.spki_sha256.is_empty() {
+            return false;
+        }
+        let subject_ok = binding.subject.is_empty() || binding.subject == self.subject;
+        let spki_ok = binding.spki_sha256.is_empty()
+            || binding.spki_sha256.eq_ignore_ascii_case(&self.spki_sha256);
+        subject_ok && spki_ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use svarog_grpc::CertBinding;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::PeerCert;
+
+    const ALICE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/alice.pem");
+    const ALICE_SUBJECT: &str = "C=CN, O=Taiyi, CN=Alice";
+    const ALICE_SPKI: &str = "dbf59506a16bacc6335584aa1fe80aefb8e3c8d87150523cb034b3245cab31e3";
+
+    fn binding(subject: &str, spki_sha256: &str) -> CertBinding {
+        CertBinding {
+            subject: subject.to_owned(),
+            spki_sha256: spki_sha256.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_from_der() -> Resultat<()> {
+        let pem = std::fs::read(ALICE).catch_()?;
+        let (_, pem) = parse_x509_pem(&pem).catch_()?;
+        let cert = PeerCert::from_der(&pem.contents).catch_()?;
+        assert_throw!(cert.subject == ALICE_SUBJECT);
+        assert_throw!(cert.spki_sha256 == ALICE_SPKI);
+        assert_throw!(PeerCert::from_der(b"not a certificate").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches() -> Resultat<()> {
+        let cert = PeerCert {
+            subject: ALICE_SUBJECT.to_owned(),
+            spki_sha256: ALICE_SPKI.to_owned(),
+        };
+        let upper = ALICE_SPKI.to_uppercase();
+        for (subject, spki) in [
+            (ALICE_SUBJECT, ""),
+            ("", ALICE_SPKI),
+            ("", upper.as_str()),
+            (ALICE_SUBJECT, ALICE_SPKI),
+        ] {
+            assert_throw!(cert.matches(&binding(subject, spki)), subject);
+        }
+        for (subject, spki) in [
+            ("", ""),
+            ("CN=Alice", ""),
+            ("", "00"),
+            (ALICE_SUBJECT, "00"),
+            ("CN=Mallory", ALICE_SPKI),
+        ] {
+            assert_throw!(!cert.matches(&binding(subject, spki)), subject);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+//! Configuration of sesman.
+//!
+//! Every item is taken from the first of these that sets it:
+//! the command line, the environment variable `SVAROG_SESMAN_<SECTION>_<KEY>`,
+//! e.g. `SVAROG_SESMAN_LIMITS_MAX_PAYLOAD`, the TOML file given by `--config`,
+//! and the defaults below.
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use erreur::*;
+use serde::{Deserialize, Serialize};
+use svarog_grpc::Limits;
+
+const ENV_PREFIX: &str = "SVAROG_SESMAN";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen: ListenConfig,
+    pub tls: TlsConfig,
+    pub sessions: SessionsConfig,
+    pub limits: LimitsConfig,
+    pub log: LogConfig,
+    pub storage: StorageConfig,
+    pub admin: AdminConfig,
+    pub audit: AuditConfig,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenConfig {
+    pub host: String,
+    pub port: u16,
+    /// Serve Prometheus metrics at `http://<metrics_addr>/metrics`. Empty means not served.
+    pub metrics_addr: String,
+    /// Serve the REST gateway at `http(s)://<rest_addr>/v1/`. Empty means not served.
+    pub rest_addr: String,
+    /// Comma separated URLs that the players reach sesman at. If not empty,
+    /// `NewSession` rejects a `sesman_url` other than these.
+    pub public_urls: String,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_owned(),
+            port: 2000,
+            metrics_addr: String::new(),
+            rest_addr: String::new(),
+            public_urls: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub https: bool,
+    /// Require client certificates issued by `client_ca`. Implies `https`.
+    pub mtls: bool,
+    pub cert: String,
+    pub key: String,
+    pub client_ca: String,
+    /// Check the files above every this many seconds and reload them once changed.
+    /// 0 means reloading only on SIGHUP.
+    pub reload_interval: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            https: false,
+            mtls: false,
+            cert: "tls/cert.pem".to_owned(),
+            key: "tls/privkey.pem".to_owned(),
+            client_ca: "tls/client_ca.pem".to_owned(),
+            reload_interval: 60,
+        }
+    }
+}
+
+/// In seconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionsConfig {
+    /// Lifetime of sessions created without `ttl`.
+    pub default_ttl: u64,
+    /// Upper bound of `ttl`.
+    pub max_ttl: u64,
+    /// How often expired sessions and messages are removed.
+    pub recycle_interval: u64,
+    /// How long to wait for the sessions in progress after SIGTERM.
+    pub drain_timeout: u64,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl: 300,
+            max_ttl: 86400,
+            recycle_interval: 60,
+            drain_timeout: 600,
+        }
+    }
+}
+
+/// 0 means unlimited. See `svarog_grpc::Limits`.
+/// Unlimited by default, as sesman has always been.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_payload: u64,
+    pub max_batch: u64,
+    pub max_msgs_per_session: u64,
+    pub max_bytes_per_session: u64,
+    pub max_sessions: u64,
+    pub max_waiters_per_conn: u64,
+    pub memory_budget: u64,
+}
+
+impl From<&LimitsConfig> for Limits {
+    fn from(lim: &LimitsConfig) -> Self {
+        Limits {
+            max_payload: lim.max_payload,
+            max_batch: lim.max_batch,
+            max_msgs_per_session: lim.max_msgs_per_session,
+            max_bytes_per_session: lim.max_bytes_per_session,
+            max_sessions: lim.max_sessions,
+            max_waiters_per_conn: lim.max_waiters_per_conn,
+            memory_budget: lim.memory_budget,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `text` or `json`.
+    pub format: String,
+    /// Filter such as `info,svarog_sesman=debug`. Overridden by `RUST_LOG`.
+    pub level: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: "text".to_owned(),
+            level: "info".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Persist sessions to this directory. Empty means in memory.
+    pub db: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Serve `SesmanAdmin` to the bearer of the token in this file. Empty means not served.
+    pub token_file: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Append the hash-chained audit log to this file. Empty means not audited.
+    pub log: String,
+}
+
+impl Config {
+    /// Read the file if given, then apply the environment variables in `env`,
+    /// which is `std::env::vars()` but in the tests.
+    pub fn load(path: Option<&str>, env: &HashMap<String, String>) -> Resultat<Self> {
+        let cfg: Config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .catch("", format!("Try reading config file {}", path))?;
+                toml::from_str(&text).catch_()?
+            }
+            None => Config::default(),
+        };
+        let mut val = toml::Value::try_from(&cfg).catch_()?;
+        let sections = val.as_table_mut().ifnone_()?;
+        for (section, items) in sections.iter_mut() {
+            let items = items.as_table_mut().ifnone_()?;
+            for (key, item) in items.iter_mut() {
+                let var = format!("{}_{}_{}", ENV_PREFIX, section, key).to_uppercase();
+                let Some(env) = env.get(&var).cloned() else {
+                    continue;
+                };
+                *item = match item {
+                    toml::Value::Boolean(_) => {
+                        toml::Value::Boolean(env.parse().catch("InvalidConfig", &var)?)
+                    }
+                    toml::Value::Integer(_) => {
+                        toml::Value::Integer(env.parse().catch("InvalidConfig", &var)?)
+                    }
+                    _ => toml::Value::String(env),
+                };
+            }
+        }
+        let cfg = val.try_into().catch_()?;
+        Ok(cfg)
+    }
+
+    pub fn validate(&self) -> Resultat<()> {
+        self.listen_addr().catch_()?;
+        if !self.listen.metrics_addr.is_empty() {
+            self.metrics_addr().catch_()?;
+        }
+        if !self.listen.rest_addr.is_empty() {
+            self.rest_addr().catch_()?;
+        }
+        for url in self.public_urls() {
+            assert_throw!(
+                url.starts_with("http://") || url.starts_with("https://"),
+                format!(
+                    "listen.public_urls {} should start with http:// or https://",
+                    url
+                )
+            );
+        }
+        let ses = &self.sessions;
+        assert_throw!(ses.max_ttl > 0, "sessions.max_ttl should be positive");
+        assert_throw!(
+            0 < ses.default_ttl && ses.default_ttl <= ses.max_ttl,
+            "sessions.default_ttl should be positive and not exceed sessions.max_ttl"
+        );
+        assert_throw!(
+            ses.recycle_interval > 0,
+            "sessions.recycle_interval should be positive"
+        );
+        assert_throw!(
+            ["text", "json"].contains(&self.log.format.as_str()),
+            format!(
+                "log.format should be text or json, got {}",
+                &self.log.format
+            )
+        );
+        if self.https() {
+            for path in [&self.tls.cert, &self.tls.key] {
+                assert_throw!(
+                    std::path::Path::new(path).is_file(),
+                    format!("TLS file {} does not exist", path)
+                );
+            }
+        }
+        if self.tls.mtls {
+            assert_throw!(
+                std::path::Path::new(&self.tls.client_ca).is_file(),
+                format!("TLS file {} does not exist", &self.tls.client_ca)
+            );
+        }
+        if !self.admin.token_file.is_empty() {
+            assert_throw!(
+                std::path::Path::new(&self.admin.token_file).is_file(),
+                format!("Admin token file {} does not exist", &self.admin.token_file)
+            );
+        }
+        Ok(())
+    }
+
+    pub fn https(&self) -> bool {
+        self.tls.https || self.tls.mtls
+    }
+
+    pub fn listen_addr(&self) -> Resultat<SocketAddr> {
+        let addr = format!("{}:{}", &self.listen.host, self.listen.port);
+        addr.parse()
+            .catch("InvalidConfig", format!("Invalid listen address {}", addr))
+    }
+
+    pub fn metrics_addr(&self) -> Resultat<SocketAddr> {
+        let addr = &self.listen.metrics_addr;
+        addr.parse()
+            .catch("InvalidConfig", format!("Invalid metrics address {}", addr))
+    }
+
+    pub fn rest_addr(&self) -> Resultat<SocketAddr> {
+        let addr = &self.listen.rest_addr;
+        addr.parse()
+            .catch("InvalidConfig", format!("Invalid REST address {}", addr))
+    }
+
+    pub fn public_urls(&self) -> Vec<String> {
+        self.listen
+            .public_urls
+            .split(',')
+            .map(|url| url.trim().to_owned())
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
+
+    pub fn to_toml(&self) -> Resultat<String> {
+        toml::to_string_pretty(self).catch_()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use erreur::*;
+
+    use super::Config;
+
+    const EXAMPLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../svarog_sesman.example.toml");
+
+    #[test]
+    fn test_example_is_default() -> Resultat<()> {
+        let example = Config::load(Some(EXAMPLE), &HashMap::new()).catch_()?;
+        assert_throw!(example.to_toml()? == Config::default().to_toml()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlimited_by_default() -> Resultat<()> {
+        let lim = Config::default().limits;
+        let all = [
+            lim.max_payload,
+            lim.max_batch,
+            lim.max_msgs_per_session,
+            lim.max_bytes_per_session,
+            lim.max_sessions,
+            lim.max_waiters_per_conn,
+            lim.memory_budget,
+        ];
+        assert_throw!(all.iter().all(|&val| val == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_over_file() -> Resultat<()> {
+        let path =
+            std::env::temp_dir().join(format!("svarog_config_{}.toml", uuid::Uuid::now_v7()));
+        let toml = "[listen]\nport = 3000\n\n[limits]\nmax_payload = 10\nmax_batch = 5\n";
+        std::fs::write(&path, toml).catch_()?;
+        let env = |max_payload: &str| -> HashMap<String, String> {
+            [
+                ("SVAROG_SESMAN_LIMITS_MAX_PAYLOAD", max_payload),
+                ("SVAROG_SESMAN_TLS_MTLS", "true"),
+            ]
+            .into_iter()
+            .map(|(var, val)| (var.to_owned(), val.to_owned()))
+            .collect()
+        };
+        let cfg = Config::load(path.to_str(), &env("20"));
+        let bad = Config::load(path.to_str(), &env("many"));
+        let _ = std::fs::remove_file(&path);
+
+        let cfg = cfg.catch_()?;
+        assert_throw!(cfg.listen.port == 3000 && cfg.sessions.max_ttl == 86400);
+        assert_throw!(cfg.limits.max_payload == 20 && cfg.limits.max_batch == 5);
+        assert_throw!(cfg.https());
+        assert_throw!(bad.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Resultat<()> {
+        Config::default().validate().catch_()?;
+        let mut cfg = Config::default();
+        cfg.listen.public_urls = " https://sesman.example/, http://10.0.0.1:2000 ,".to_owned();
+        cfg.validate().catch_()?;
+        assert_throw!(cfg.public_urls() == ["https://sesman.example/", "http://10.0.0.1:2000"]);
+
+        let invalid: [fn(&mut Config); 6] = [
+            |cfg| cfg.listen.host = "sesman.example".to_owned(),
+            |cfg| cfg.listen.public_urls = "sesman.example:2000".to_owned(),
+            |cfg| cfg.sessions.default_ttl = cfg.sessions.max_ttl + 1,
+            |cfg| cfg.sessions.recycle_interval = 0,
+            |cfg| cfg.log.format = "xml".to_owned(),
+            |cfg| cfg.admin.token_file = "/nonexistent/admin.token".to_owned(),
+        ];
+        for (i, edit) in invalid.iter().enumerate() {
+            let mut cfg = Config::default();
+            edit(&mut cfg);
+            assert_throw!(cfg.validate().is_err(), format!("case {}", i));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Graceful shutdown of sesman.
+//!
+//! On SIGTERM or Ctrl-C sesman drains: it reports not serving, refuses new sessions,
+//! and keeps serving the sessions in progress until they all finish or the drain
+//! timeout passes. A second signal cuts the drain short.
+
+use std::time::Duration;
+
+use svarog_grpc::mpc_session_manager_server::MpcSessionManagerServer;
+use tokio::time::{sleep, Instant};
+use tokio_stream::{Stream, StreamExt};
+use tonic_health::{server::HealthReporter, ServingStatus};
+use tracing::{debug, error, info, warn};
+
+use crate::Sesman;
+
+/// How often the unfinished sessions are counted.
+const DRAIN_POLL: Duration = Duration::from_secs(1);
+
+/// Resolves once sesman should stop serving. Each item of `stop` is a signal to stop,
+/// such as SIGTERM or Ctrl-C.
+pub async fn drain<S>(sesman: Sesman, mut health: HealthReporter, mut stop: S, timeout: Duration)
+where
+    S: Stream<Item = ()> + Unpin,
+{
+    if stop.next().await.is_none() {
+        // Never signaled.
+        return std::future::pending().await;
+    }
+    info!(
+        "Draining: refuse new sessions, and exit once the others finish or in {:?}",
+        timeout
+    );
+    health
+        .set_service_status("", ServingStatus::NotServing)
+        .await;
+    health
+        .set_not_serving::<MpcSessionManagerServer<Sesman>>()
+        .await;
+    sesman.start_draining();
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match sesman.unfinished_sessions() {
+            Ok(0) => {
+                info!("All sessions finished, shutting down");
+                return;
+            }
+            Ok(n) => debug!("Waiting for {} sessions to finish", n),
+            Err(e) => error!("Failed to count unfinished sessions: {}", e),
+        }
+        if Instant::now() >= deadline {
+            warn!("Drain timeout passed, shutting down with sessions unfinished");
+            return;
+        }
+        tokio::select! {
+            _ = sleep(DRAIN_POLL) => {}
+            Some(()) = stop.next() => {
+                warn!("Signaled again, shutting down without draining");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use erreur::*;
+    use svarog_grpc::{mpc_session_manager_server::MpcSessionManager, SessionId};
+    use tokio::{
+        sync::mpsc,
+        time::{sleep, timeout},
+    };
+    use tokio_stream::wrappers::ReceiverStream;
+    use tonic::{Code, Request};
+    use tonic_health::pb::{
+        health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+    };
+
+    use super::drain;
+    use crate::{
+        server_health::tests::serve,
+        server_impl::tests::{players, request, sesman, settings},
+    };
+
+    #[tokio::test]
+    async fn test_drain() -> Resultat<()> {
+        let running = sesman(settings()).await?;
+        let sid = running
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let (reporter, channel) = serve(false).await?;
+        let mut health = HealthClient::new(channel);
+        let (stop, stops) = mpsc::channel(1);
+        let drained = tokio::spawn(drain(
+            running.clone(),
+            reporter,
+            ReceiverStream::new(stops),
+            Duration::from_secs(60),
+        ));
+        sleep(Duration::from_millis(200)).await;
+        assert_throw!(!drained.is_finished());
+        stop.send(()).await.catch_()?;
+        sleep(Duration::from_millis(200)).await;
+
+        for service in ["", "svarog.MpcSessionManager"] {
+            let req = HealthCheckRequest {
+                service: service.to_owned(),
+            };
+            let resp = health.check(req).await.catch_()?.into_inner();
+            assert_throw!(resp.status() == ServingStatus::NotServing, service);
+        }
+        let res = running
+            .new_session(Request::new(players(&["A", "B"])))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::Unavailable));
+
+        // The session in progress goes on until it completes.
+        let session_id = || SessionId {
+            value: sid.value.clone(),
+            ..Default::default()
+        };
+        for name in ["A", "B"] {
+            assert_throw!(!drained.is_finished());
+            running
+                .complete_session(request(session_id(), &sid.tokens[name]))
+                .await
+                .catch_()?;
+        }
+        timeout(Duration::from_secs(3), drained)
+            .await
+            .catch("", "Drain did not end once the sessions finished")?
+            .catch_()?;
+
+        // Or until the drain timeout passes.
+        let stuck = sesman(settings()).await?;
+        stuck
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?;
+        let (reporter, _channel) = serve(false).await?;
+        let (stop, stops) = mpsc::channel(1);
+        let drained = tokio::spawn(drain(
+            stuck.clone(),
+            reporter,
+            ReceiverStream::new(stops),
+            Duration::from_secs(1),
+        ));
+        stop.send(()).await.catch_()?;
+        timeout(Duration::from_secs(4), drained)
+            .await
+            .catch("", "Drain did not end at its timeout")?
+            .catch_()?;
+
+        // Or until signaled again.
+        let (reporter, _channel) = serve(false).await?;
+        let (stop, stops) = mpsc::channel(1);
+        let drained = tokio::spawn(drain(
+            stuck,
+            reporter,
+            ReceiverStream::new(stops),
+            Duration::from_secs(60),
+        ));
+        stop.send(()).await.catch_()?;
+        sleep(Duration::from_millis(200)).await;
+        assert_throw!(!drained.is_finished());
+        stop.send(()).await.catch_()?;
+        timeout(Duration::from_secs(1), drained)
+            .await
+            .catch("", "Drain did not end when signaled again")?
+            .catch_()?;
+        Ok(())
+    }
+}
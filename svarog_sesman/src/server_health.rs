@@ -0,0 +1,133 @@
+//! Standard gRPC health checking and reflection, for load balancers and tools like grpcurl.
+
+use erreur::*;
+use svarog_grpc::{
+    mpc_session_manager_server::MpcSessionManagerServer, sesman_admin_server::SesmanAdminServer,
+};
+use tonic_health::{
+    pb::health_server::{Health, HealthServer},
+    server::HealthReporter,
+    ServingStatus,
+};
+use tonic_reflection::server::{ServerReflection, ServerReflectionServer};
+
+use crate::{server_admin::Admin, Sesman};
+
+/// Report sesman as serving, under the empty name and the name of each service,
+/// `SesmanAdmin` included if `admin` is served.
+pub async fn health_service(admin: bool) -> (HealthReporter, HealthServer<impl Health>) {
+    let (mut health, service) = tonic_health::server::health_reporter();
+    health.set_service_status("", ServingStatus::Serving).await;
+    health
+        .set_serving::<MpcSessionManagerServer<Sesman>>()
+        .await;
+    if admin {
+        health.set_serving::<SesmanAdminServer<Admin>>().await;
+    }
+    (health, service)
+}
+
+pub fn reflection_service() -> Resultat<ServerReflectionServer<impl ServerReflection>> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(svarog_grpc::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()
+        .catch_()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use erreur::*;
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+    use tonic::{
+        transport::{server::TcpIncoming, Channel, Server},
+        Code,
+    };
+    use tonic_health::{
+        pb::{
+            health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+        },
+        server::HealthReporter,
+    };
+    use tonic_reflection::pb::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        ServerReflectionRequest,
+    };
+
+    use super::{health_service, reflection_service};
+
+    /// Serve health checking and reflection on a free port, and connect to it.
+    pub(crate) async fn serve(admin: bool) -> Resultat<(HealthReporter, Channel)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.catch_()?;
+        let url = format!("http://{}", listener.local_addr().catch_()?);
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .ok()
+            .ifnone("", "Failed to listen")?;
+        let (health, service) = health_service(admin).await;
+        let router = Server::builder()
+            .add_service(service)
+            .add_service(reflection_service()?);
+        tokio::spawn(router.serve_with_incoming(incoming));
+        let channel = Channel::from_shared(url)
+            .catch_()?
+            .connect()
+            .await
+            .catch_()?;
+        Ok((health, channel))
+    }
+
+    #[tokio::test]
+    async fn test_health() -> Resultat<()> {
+        for admin in [false, true] {
+            let (_health, channel) = serve(admin).await?;
+            let mut client = HealthClient::new(channel);
+            let check = |service: &str| HealthCheckRequest {
+                service: service.to_owned(),
+            };
+            for service in ["", "svarog.MpcSessionManager"] {
+                let resp = client.check(check(service)).await.catch_()?.into_inner();
+                assert_throw!(resp.status() == ServingStatus::Serving, service);
+            }
+            let res = client.check(check("svarog.SesmanAdmin")).await;
+            match res {
+                Ok(resp) => {
+                    assert_throw!(admin && resp.into_inner().status() == ServingStatus::Serving)
+                }
+                Err(status) => assert_throw!(!admin && status.code() == Code::NotFound),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reflection_lists_services() -> Resultat<()> {
+        let (_health, channel) = serve(false).await?;
+        let mut client = ServerReflectionClient::new(channel);
+        let req = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let mut replies = client
+            .server_reflection_info(tokio_stream::iter([req]))
+            .await
+            .catch_()?
+            .into_inner();
+        let reply = replies.next().await.ifnone_()?.catch_()?;
+        let list = match reply.message_response {
+            Some(MessageResponse::ListServicesResponse(list)) => Some(list),
+            _ => None,
+        }
+        .ifnone("", "Unexpected reflection reply")?;
+        let names: Vec<String> = list.service.into_iter().map(|svc| svc.name).collect();
+        for name in [
+            "svarog.MpcSessionManager",
+            "svarog.SesmanAdmin",
+            "grpc.health.v1.Health",
+        ] {
+            assert_throw!(names.iter().any(|n| n == name), name);
+        }
+        Ok(())
+    }
+}
@@ -1,39 +1,111 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use blake2::digest::{Update, VariableOutput};
 use crossbeam_skiplist::SkipMap;
 use erreur::*;
+use serde_json::{json, Value};
 use svarog_grpc::{
-    mpc_session_manager_server::MpcSessionManager, EchoMessage, Message, SessionConfig, SessionId,
-    VecMessage, Void,
+    mpc_session_manager_server::MpcSessionManager, AbortRequest, EchoMessage, Equivocation,
+    ExchangeReply, JoinReply, Limits, Message, PostedMessage, SessionConfig, SessionId,
+    SessionState, SessionStatus, SessionSummary, Usage, VecEquivocation, VecMessage, Void,
+    WaitingMessage,
 };
+use svarog_sesman::{config_digest, same_sesman_url, validate_session_config, E2E_KEY_TOPIC};
 use tokio::{
+    sync::{mpsc, Notify},
     task::JoinHandle,
-    time::{sleep, Duration},
+    time::{sleep, timeout, Duration, Instant},
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{metadata::MetadataMap, Code, Request, Response, Status, Streaming};
+use tracing::{debug, error, field::Empty, info, instrument, warn, Instrument, Level, Span};
+
+use crate::{
+    server_audit::AuditLog,
+    server_cert::PeerCert,
+    server_metrics::Metrics,
+    server_rest::RestPeer,
+    server_session::{now_ms, Grant, SessionRecord},
+    server_status::Waiters,
+    server_storage::Storage,
+    server_usage::Accounting,
 };
-use tonic::{Request, Response, Status};
-
-pub fn pivot_key() -> [u8; 32] {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let now = SystemTime::now();
-    let t = now
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_millis()
-        - svarog_sesman::SESSION_EXPIRE_MS;
-    let mut pivot = [0u8; 32];
-    pivot[..6].copy_from_slice(&t.to_be_bytes()[10..16]);
+
+const EXCHANGE_BUFFER: usize = 64;
+
+/// Keys not greater than the pivot belong to sessions expired at `now`.
+pub fn pivot_key(now: u64) -> [u8; 32] {
+    let mut pivot = [0xffu8; 32];
+    pivot[..6].copy_from_slice(&now.to_be_bytes()[2..8]);
 
     pivot
 }
 
-pub fn primary_key(sid: &str, topic: &str, src: u64, dst: u64, seq: u64) -> Resultat<[u8; 32]> {
-    let mut pk = [0u8; 32];
+/// Reply of `Exchange` about `msg`, which failed if `status` is given.
+fn exchange_reply(msg: Message, status: Option<Status>) -> ExchangeReply {
+    let (code, error) = match status {
+        Some(status) => (status.code() as i32, status.message().to_owned()),
+        None => (0, String::new()),
+    };
+    ExchangeReply {
+        msg: Some(msg),
+        code,
+        error,
+    }
+}
+
+pub const MAX_SESSION_ID_LEN: usize = 128;
+
+/// Session ids are chosen by sesman or by users, e.g. business order numbers.
+#[allow(clippy::result_large_err)]
+pub fn validate_session_id(sid: &str) -> Result<(), Status> {
+    if sid.is_empty() || sid.len() > MAX_SESSION_ID_LEN {
+        return Err(Status::invalid_argument(format!(
+            "Session id should contain 1 to {} characters, got {}",
+            MAX_SESSION_ID_LEN,
+            sid.len()
+        )));
+    }
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || "-_.:".contains(c);
+    if let Some(c) = sid.chars().find(|&c| !valid_char(c)) {
+        return Err(Status::invalid_argument(format!(
+            "Session id {:?} contains invalid character {:?}; \
+            only ASCII letters, digits, '-', '_', '.' and ':' are allowed",
+            sid, c
+        )));
+    }
+    Ok(())
+}
 
-    // sid
-    let sid = hex::decode(sid).catch_()?;
-    assert_throw!(sid.len() == 16);
-    pk[0..16].copy_from_slice(&sid);
+/// The handle begins with the expiry time of the session,
+/// so that the messages of expired sessions are at the front of the keyspace.
+/// The rest of the handle is a digest of the session id.
+pub fn session_handle(sid: &str, expire_at: u64) -> Resultat<[u8; 16]> {
+    let mut handle = [0u8; 16];
+    handle[..6].copy_from_slice(&expire_at.to_be_bytes()[2..8]);
+
+    let mut ha = blake2::Blake2bVar::new(10).catch_()?;
+    ha.update(sid.as_bytes());
+    ha.finalize_variable(&mut handle[6..]).catch_()?;
+
+    Ok(handle)
+}
+
+pub fn primary_key(
+    handle: &[u8; 16],
+    topic: &str,
+    src: u64,
+    dst: u64,
+    seq: u64,
+) -> Resultat<[u8; 32]> {
+    let mut pk = [0u8; 32];
+    pk[0..16].copy_from_slice(handle);
 
     // message index
     let mut ha = blake2::Blake2bVar::new(16).catch_()?;
@@ -43,12 +115,148 @@ pub fn primary_key(sid: &str, topic: &str, src: u64, dst: u64, seq: u64) -> Resu
     Ok(pk)
 }
 
-#[derive(Clone, Default)]
-pub struct Sesman(Arc<SkipMap<[u8; 32], Vec<u8>>>);
+/// Read the token from metadata `authorization: Bearer <token>`.
+#[allow(clippy::result_large_err)]
+pub fn bearer_token(meta: &MetadataMap) -> Result<String, Status> {
+    let value = meta
+        .get("authorization")
+        .ok_or_else(|| Status::unauthenticated("Missing authorization token"))?;
+    let value = value
+        .to_str()
+        .map_err(|_| Status::unauthenticated("Malformed authorization token"))?;
+    let token = value
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| Status::unauthenticated("Expect a bearer token"))?;
+    Ok(token.trim().to_owned())
+}
 
+/// Who is calling, as far as sesman can tell.
+#[derive(Clone)]
+pub struct Caller {
+    token: String,
+    cert: Option<PeerCert>,
+    /// Remote address of the connection.
+    conn: String,
+}
+
+#[allow(clippy::result_large_err)]
+impl Caller {
+    pub fn of_request<T>(req: &Request<T>) -> Result<Self, Status> {
+        let token = bearer_token(req.metadata())?;
+        let cert = PeerCert::of_request(req)
+            .catch_()
+            .map_err(|e| Status::unauthenticated(e.to_string()))?;
+        let conn = remote_conn(req);
+        Ok(Self { token, cert, conn })
+    }
+}
+
+/// Remote address of the connection, over gRPC or the REST gateway.
+fn remote_conn<T>(req: &Request<T>) -> String {
+    req.remote_addr()
+        .or_else(|| req.extensions().get::<RestPeer>().map(|peer| peer.0))
+        .map(|addr| addr.to_string())
+        .unwrap_or_default()
+}
+
+/// Find what the caller may do in the session.
+/// If the token belongs to a player bound to a certificate,
+/// the caller should also have presented that certificate.
+#[allow(clippy::result_large_err)]
+fn authorize<'a>(rec: &'a SessionRecord, caller: &Caller) -> Result<&'a Grant, Status> {
+    let sid = &rec.cfg.session_id;
+    let grant = rec
+        .grant(&caller.token)
+        .ok_or_else(|| Status::unauthenticated(format!("Invalid token for session {}", sid)))?;
+    if let Some(binding) = rec.cfg.player_certs.get(&grant.name) {
+        let cert = caller.cert.as_ref().ok_or_else(|| {
+            Status::unauthenticated(format!(
+                "Player {:?} of session {} should present a client certificate",
+                &grant.name, sid
+            ))
+        })?;
+        if !cert.matches(binding) {
+            return Err(Status::permission_denied(format!(
+                "Client certificate {:?} is not bound to player {:?} of session {}",
+                &cert.subject, &grant.name, sid
+            )));
+        }
+    }
+    Span::current().record("player", grant.name.as_str());
+    Ok(grant)
+}
+
+/// What became of a posted message.
+enum Posted {
+    /// Stored by this post.
+    Stored,
+    /// Found with the same payload, stored earlier.
+    Resent,
+    /// Found with another payload.
+    Conflicting,
+}
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// Lifetime in seconds of sessions created without `ttl`.
+    pub default_ttl: u64,
+    /// Upper bound in seconds of `ttl`.
+    pub max_ttl: u64,
+    /// Seconds between two runs of the recycle task.
+    pub recycle_interval: u64,
+    /// Whether clients present certificates issued by the configured CA.
+    pub mtls: bool,
+    /// URLs that the players reach sesman at, if known.
+    pub public_urls: Vec<String>,
+    pub limits: Limits,
+}
+
+#[derive(Clone)]
+pub struct Sesman {
+    settings: Arc<Settings>,
+    store: Arc<dyn Storage>,
+    usage: Arc<Accounting>,
+    waiters: Arc<Waiters>,
+    /// Wakes up the `outbox` waiters of a session, keyed by the session handle.
+    notifiers: Arc<SkipMap<[u8; 16], Arc<Notify>>>,
+    rec_lock: Arc<Mutex<()>>,
+    /// Held from auditing a change of a session record to saving it,
+    /// so that each change is audited once, and before it is saved.
+    audit_lock: Arc<tokio::sync::Mutex<()>>,
+    metrics: Arc<Metrics>,
+    /// Refuse new sessions while those in progress finish.
+    draining: Arc<AtomicBool>,
+    audit: Arc<AuditLog>,
+}
+
+// The helpers hand their tonic::Status on to the handlers as is.
+#[allow(clippy::result_large_err)]
 impl Sesman {
-    pub async fn init() -> Resultat<(Self, JoinHandle<()>)> {
-        let sesman = Sesman::default();
+    pub async fn init(
+        settings: Settings,
+        store: Arc<dyn Storage>,
+        audit: Arc<AuditLog>,
+    ) -> Resultat<(Self, JoinHandle<()>)> {
+        let usage = Arc::new(Accounting::new(settings.limits.clone()));
+        let now = now_ms();
+        for (_, rec) in store.sessions().catch_()? {
+            let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default()).catch_()?;
+            if !rec.is_expired(now) {
+                usage.restore_session(&rec.handle);
+            }
+        }
+        let sesman = Sesman {
+            settings: Arc::new(settings),
+            store,
+            usage,
+            waiters: Arc::new(Waiters::default()),
+            notifiers: Arc::new(SkipMap::new()),
+            rec_lock: Arc::new(Mutex::new(())),
+            audit_lock: Arc::new(tokio::sync::Mutex::new(())),
+            metrics: Arc::new(Metrics::new().catch_()?),
+            draining: Arc::new(AtomicBool::new(false)),
+            audit,
+        };
         let h = tokio::spawn(sesman.clone().recycle());
 
         Ok((sesman, h))
@@ -56,118 +264,2015 @@ impl Sesman {
 
     async fn recycle(self) {
         loop {
-            let pivot = pivot_key();
-            while let Some(entry) = self.0.front() {
-                let k = entry.key().clone();
-                if k > pivot {
-                    // recently added item
-                    break;
-                } else {
-                    // outdated item
-                    let _ = entry.remove();
+            if let Err(e) = self.recycle_once() {
+                error!("Failed to recycle outdated items: {}", e);
+            }
+            sleep(Duration::from_secs(self.settings.recycle_interval)).await;
+        }
+    }
+
+    fn recycle_once(&self) -> Resultat<()> {
+        let now = now_ms();
+        for (sid, rec) in self.store.sessions().catch_()? {
+            let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default()).catch_()?;
+            if !rec.is_expired(now) {
+                continue;
+            }
+            // Checked again under the lock, as the session id may have been taken
+            // by a new session in the meantime.
+            let rec = {
+                let _guard = self.rec_lock.lock().unwrap_or_else(|e| e.into_inner());
+                let Some(rec) = self.store.get_session(&sid).catch_()? else {
+                    continue;
+                };
+                let rec: SessionRecord =
+                    serde_pickle::from_slice(&rec, Default::default()).catch_()?;
+                if !rec.is_expired(now) {
+                    continue;
+                }
+                self.store.remove_session(&sid).catch_()?;
+                rec
+            };
+            self.metrics.sessions_expired.inc();
+            info!(session_id = %sid, "session expired");
+            if rec.finished_at == 0 && rec.aborted.is_none() {
+                let detail = json!({
+                    "state": format!("{:?}", rec.state),
+                    "joined": rec.joined.keys().collect::<Vec<_>>(),
+                    "completed": rec.completed.keys().collect::<Vec<_>>(),
+                });
+                self.audit
+                    .record_or_log("session_expired", &sid, "", detail);
+            }
+        }
+
+        let pivot = pivot_key(now);
+        let n = self.store.remove_until(&pivot).catch_()?;
+        self.metrics.recycled_entries.inc_by(n as u64);
+        if n > 0 {
+            info!(removed = n, "recycled expired messages");
+        }
+        let mut pivot_handle = [0u8; 16];
+        pivot_handle.copy_from_slice(&pivot[..16]);
+        self.usage.release_until(&pivot_handle);
+        while let Some(entry) = self.notifiers.front() {
+            if entry.key()[..] > pivot[..16] {
+                break;
+            }
+            let _ = entry.remove();
+        }
+        Ok(())
+    }
+
+    /// Current resource usage, along with the limits.
+    pub fn usage(&self) -> Usage {
+        self.usage.usage()
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Update the gauges, right before they are scraped.
+    pub fn sample_metrics(&self) {
+        let usage = self.usage.usage();
+        self.metrics.sessions_active.set(usage.sessions as i64);
+        self.metrics.waiters.set(usage.waiters as i64);
+        match self.store.len() {
+            Ok(n) => self.metrics.store_entries.set(n as i64),
+            Err(e) => error!("Failed to count the stored messages: {}", e),
+        }
+    }
+
+    /// Like `wait`, but also observe the time waited.
+    /// Waits that fail or get cancelled are not observed.
+    async fn timed_wait(
+        &self,
+        rpc: &str,
+        idxs: &[Message],
+        caller: &Caller,
+    ) -> Result<Vec<Message>, Status> {
+        let since = Instant::now();
+        let msgs = self.wait(idxs, caller).await?;
+        self.metrics
+            .wait_seconds
+            .with_label_values(&[rpc])
+            .observe(since.elapsed().as_secs_f64());
+        self.metrics.observe_msgs(rpc, "out", &msgs);
+        Ok(msgs)
+    }
+
+    fn notifier(&self, handle: &[u8; 16]) -> Arc<Notify> {
+        self.notifiers
+            .get_or_insert_with(*handle, || Arc::new(Notify::new()))
+            .value()
+            .clone()
+    }
+
+    fn load_session(&self, sid: &str) -> Result<SessionRecord, Status> {
+        validate_session_id(sid)?;
+        Span::current().record("session_id", sid);
+        let rec = self
+            .store
+            .get_session(sid)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("Session {} does not exist", sid)))?;
+        let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default())
+            .catch_()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if rec.is_expired(now_ms()) {
+            return Err(Status::deadline_exceeded(format!(
+                "Session {} has expired",
+                sid
+            )));
+        }
+        Ok(rec)
+    }
+
+    /// Like `load_session`, but also reject finished sessions.
+    fn live_session(&self, sid: &str) -> Result<SessionRecord, Status> {
+        let rec = self.load_session(sid)?;
+        match rec.state_at(now_ms()) {
+            SessionState::Aborted => Err(Status::aborted(format!(
+                "Session {} was aborted: {}",
+                sid,
+                rec.aborted.as_deref().unwrap_or_default()
+            ))),
+            SessionState::Completed => Err(Status::failed_precondition(format!(
+                "Session {} has completed",
+                sid
+            ))),
+            _ => Ok(rec),
+        }
+    }
+
+    /// Load the live sessions that the messages belong to.
+    fn live_sessions(&self, msgs: &[Message]) -> Result<HashMap<String, SessionRecord>, Status> {
+        let mut recs = HashMap::new();
+        for msg in msgs.iter() {
+            if !recs.contains_key(&msg.session_id) {
+                let rec = self.live_session(&msg.session_id)?;
+                recs.insert(msg.session_id.clone(), rec);
+            }
+        }
+        Ok(recs)
+    }
+
+    fn save_session(&self, rec: &SessionRecord) -> Result<(), Status> {
+        let val = serde_pickle::to_vec(rec, Default::default())
+            .catch_()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.store
+            .insert_session(&rec.cfg.session_id, val)
+            .and_then(|_| self.store.flush())
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Read, modify and write a live session record, one writer at a time.
+    fn update_session<F>(&self, sid: &str, f: F) -> Result<SessionRecord, Status>
+    where
+        F: FnOnce(&mut SessionRecord) -> Result<(), Status>,
+    {
+        let _guard = self.rec_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rec = self.live_session(sid)?;
+        f(&mut rec)?;
+        self.save_session(&rec)?;
+        Ok(rec)
+    }
+
+    /// Audit the request refused, and return its status.
+    fn denied(&self, sid: &str, player: &str, caller: &Caller, status: Status) -> Status {
+        let detail = json!({
+            "code": format!("{:?}", status.code()),
+            "message": status.message(),
+            "conn": &caller.conn,
+        });
+        self.audit
+            .record_or_log("access_denied", sid, player, detail);
+        status
+    }
+
+    /// Like `authorize`, but audit the refusal.
+    fn authorize_audited<'a>(
+        &self,
+        rec: &'a SessionRecord,
+        caller: &Caller,
+    ) -> Result<&'a Grant, Status> {
+        authorize(rec, caller)
+            .map_err(|status| self.denied(&rec.cfg.session_id, "", caller, status))
+    }
+
+    /// Note the player as joined at its first authorized request, other than the
+    /// reports, even if it never calls `JoinSession`, and audit it once. Audited
+    /// before noted, so that a failed audit is tried again at the next request.
+    async fn note_joined(
+        &self,
+        rec: &SessionRecord,
+        name: &str,
+        caller: &Caller,
+    ) -> Result<(), Status> {
+        if rec.joined.contains_key(name) {
+            return Ok(());
+        }
+        let sid = &rec.cfg.session_id;
+        let _audit_guard = self.audit_lock.lock().await;
+        if self.load_session(sid)?.joined.contains_key(name) {
+            return Ok(());
+        }
+        self.audit_record("player_joined", sid, name, json!({ "conn": &caller.conn }))
+            .await?;
+        let _guard = self.rec_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rec = self.load_session(sid)?;
+        rec.join(name, now_ms());
+        self.save_session(&rec)
+    }
+
+    async fn audit_record(
+        &self,
+        event: &str,
+        sid: &str,
+        player: &str,
+        detail: Value,
+    ) -> Result<(), Status> {
+        self.audit
+            .record(event, sid, player, detail)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// Audit the session refused to be created, and return its status.
+    fn rejected(&self, sid: &str, status: Status) -> Status {
+        let detail = json!({
+            "code": format!("{:?}", status.code()),
+            "message": status.message(),
+        });
+        self.audit
+            .record_or_log("session_rejected", sid, "", detail);
+        status
+    }
+
+    /// Reject configs unfit for their kind of session, or meant for another sesman.
+    fn check_config(&self, cfg: &SessionConfig) -> Result<(), Status> {
+        validate_session_config(cfg, cfg.kind())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let url = &cfg.sesman_url;
+        if url.is_empty() {
+            return Ok(());
+        }
+        // Not checked against the scheme sesman serves, since a proxy in between
+        // may terminate TLS. Only `public_urls` tells what the players reach.
+        let urls = &self.settings.public_urls;
+        if !urls.is_empty() && !urls.iter().any(|known| same_sesman_url(known, url)) {
+            return Err(Status::invalid_argument(format!(
+                "sesman_url {} is none of the URLs of sesman {:?}",
+                url, urls
+            )));
+        }
+        Ok(())
+    }
+
+    /// Store the messages, and wake up those who are waiting for them.
+    /// The token holder may only send as itself.
+    async fn post(&self, msgs: &[Message], caller: &Caller) -> Result<(), Status> {
+        self.usage.admit_batch(msgs.len())?;
+        let recs = self.live_sessions(msgs)?;
+        let mut senders = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            let sid = &msg.session_id;
+            let grant = self.authorize_audited(&recs[sid], caller)?;
+            if !grant.indices.contains(&msg.src) {
+                let status = Status::permission_denied(format!(
+                    "Player {:?} cannot send messages as index {}",
+                    &grant.name, msg.src
+                ));
+                return Err(self.denied(sid, &grant.name, caller, status));
+            }
+            if let Some(owner) = msg.topic.strip_prefix(E2E_KEY_TOPIC) {
+                if owner != grant.name {
+                    let status = Status::permission_denied(format!(
+                        "Player {:?} cannot post the E2E key of player {:?}",
+                        &grant.name, owner
+                    ));
+                    return Err(self.denied(sid, &grant.name, caller, status));
+                }
+            }
+            senders.push(grant.name.clone());
+        }
+        let joiners: BTreeSet<(&String, &String)> = msgs
+            .iter()
+            .map(|msg| &msg.session_id)
+            .zip(senders.iter())
+            .collect();
+        for (sid, name) in joiners {
+            self.note_joined(&recs[sid], name, caller).await?;
+        }
+        let mut keys = Vec::with_capacity(msgs.len());
+        let mut sizes = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            let handle = &recs[&msg.session_id].handle;
+            let key = primary_key(handle, &msg.topic, msg.src, msg.dst, msg.seq)
+                .catch_()
+                .map_err(|e| Status::internal(e.to_string()))?;
+            // Re-sent messages are not counted again.
+            let stored = self
+                .store
+                .get(&key)
+                .map_err(|e| Status::internal(e.to_string()))?
+                .is_some();
+            let charge = if stored {
+                None
+            } else {
+                let size = msg.obj.as_ref().map_or(0, |obj| obj.len());
+                sizes.push((*handle, size as u64));
+                Some((*handle, size as u64))
+            };
+            keys.push((key, charge));
+        }
+        // Counted before stored, so that a batch is admitted either as a whole or not at all.
+        self.usage.admit_msgs(&sizes)?;
+
+        // Slots are write-once. Re-sending the same payload is harmless.
+        // Messages not stored by this post after all are not counted, such as
+        // those that a concurrent post of the same message stored first.
+        let mut res = Ok(());
+        let mut refunds = Vec::new();
+        let arrived_at = now_ms();
+        for ((msg, sender), (key, charge)) in msgs.iter().zip(senders.iter()).zip(keys) {
+            if res.is_ok() {
+                match self.store_msg(msg, key, arrived_at) {
+                    Ok(Posted::Stored) => continue,
+                    Ok(Posted::Resent) => {}
+                    Ok(Posted::Conflicting) => res = Err(self.report_equivocation(msg, sender)),
+                    Err(status) => res = Err(status),
+                }
+            }
+            refunds.extend(charge);
+        }
+        self.usage.refund_msgs(&refunds);
+        self.store
+            .flush()
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        // Wake up the waiters only after the messages are stored.
+        for rec in recs.values() {
+            self.notifier(&rec.handle).notify_waiters();
+        }
+        res
+    }
+
+    /// Store the message unless its slot is taken.
+    fn store_msg(&self, msg: &Message, key: [u8; 32], arrived_at: u64) -> Result<Posted, Status> {
+        let obj = msg
+            .obj
+            .as_ref()
+            .ifnone_()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let found = self
+            .store
+            .insert_once(key, obj.clone())
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if found.as_ref().is_some_and(|found| found != obj) {
+            return Ok(Posted::Conflicting);
+        }
+        let resent = found.is_some();
+        debug!(
+            topic = %msg.topic,
+            src = msg.src,
+            dst = msg.dst,
+            seq = msg.seq,
+            size = obj.len(),
+            resent,
+            "posted"
+        );
+        if !resent {
+            let meta = PostedMessage {
+                topic: msg.topic.clone(),
+                src: msg.src,
+                dst: msg.dst,
+                seq: msg.seq,
+                size: obj.len() as u64,
+                arrived_at,
+            };
+            serde_pickle::to_vec(&meta, Default::default())
+                .catch_()
+                .and_then(|meta| self.store.insert_meta(key, meta))
+                .map_err(|e| Status::internal(e.to_string()))?;
+        }
+        Ok(if resent {
+            Posted::Resent
+        } else {
+            Posted::Stored
+        })
+    }
+
+    /// Record that `msg.src` sent different payloads to the slot of `msg`.
+    fn report_equivocation(&self, msg: &Message, sender: &str) -> Status {
+        warn!(
+            topic = %msg.topic,
+            src = msg.src,
+            dst = msg.dst,
+            seq = msg.seq,
+            "equivocation"
+        );
+        let res = self.update_session(&msg.session_id, |rec| {
+            let known = rec.equivocations.iter().any(|e| {
+                (&e.topic, e.src, e.dst, e.seq) == (&msg.topic, msg.src, msg.dst, msg.seq)
+            });
+            if !known {
+                rec.equivocations.push(Equivocation {
+                    topic: msg.topic.clone(),
+                    src: msg.src,
+                    dst: msg.dst,
+                    seq: msg.seq,
+                    detected_at: now_ms(),
+                });
+            }
+            Ok(())
+        });
+        if let Err(status) = res {
+            return status;
+        }
+        let detail = json!({
+            "topic": &msg.topic,
+            "src": msg.src,
+            "dst": msg.dst,
+            "seq": msg.seq,
+        });
+        self.audit
+            .record_or_log("equivocation", &msg.session_id, sender, detail);
+        Status::already_exists(format!(
+            "Player {} sent a different message to slot {}-{}-{}-{} of session {}",
+            msg.src, &msg.topic, msg.src, msg.dst, msg.seq, &msg.session_id
+        ))
+    }
+
+    /// Messages posted to the session, in the order of arrival.
+    fn posted_messages(&self, handle: &[u8; 16]) -> Result<Vec<PostedMessage>, Status> {
+        let mut posted = Vec::new();
+        for meta in self
+            .store
+            .scan_meta(handle)
+            .map_err(|e| Status::internal(e.to_string()))?
+        {
+            let meta: PostedMessage = serde_pickle::from_slice(&meta, Default::default())
+                .catch_()
+                .map_err(|e| Status::internal(e.to_string()))?;
+            posted.push(meta);
+        }
+        posted.sort_by_key(|meta| meta.arrived_at);
+        Ok(posted)
+    }
+
+    /// Messages being waited for in the session, but not posted yet.
+    fn waiting_messages(&self, handle: &[u8; 16]) -> Result<Vec<WaitingMessage>, Status> {
+        let mut waiting = Vec::new();
+        for (key, msg) in self.waiters.of_session(handle) {
+            let arrived = self
+                .store
+                .get(&key)
+                .map_err(|e| Status::internal(e.to_string()))?
+                .is_some();
+            if !arrived {
+                waiting.push(msg);
+            }
+        }
+        Ok(waiting)
+    }
+
+    fn summarize(&self, rec: SessionRecord) -> Result<SessionSummary, Status> {
+        let now = now_ms();
+        let posted = self.posted_messages(&rec.handle)?;
+        let waiting = self.waiting_messages(&rec.handle)?;
+        let participants = rec.participants();
+        let joined = participants.iter().filter(|p| rec.joined.contains_key(*p));
+        let completed = participants
+            .iter()
+            .filter(|p| rec.completed.contains_key(*p));
+        let mut cfg = rec.cfg.clone();
+        cfg.ttl_remaining = rec.remaining_ms(now).div_ceil(1000);
+        Ok(SessionSummary {
+            config: Some(cfg),
+            state: rec.state_at(now) as i32,
+            created_at: rec.created_at,
+            expire_at: rec.expire_at,
+            age_ms: now.saturating_sub(rec.created_at),
+            messages: posted.len() as u64,
+            bytes: posted.iter().map(|meta| meta.size).sum(),
+            joined: joined.count() as u64,
+            completed: completed.count() as u64,
+            participants: participants.len() as u64,
+            waiting: waiting.len() as u64,
+            equivocations: rec.equivocations.len() as u64,
+        })
+    }
+
+    /// Sessions not expired yet, whatever their states.
+    pub fn session_summaries(&self) -> Result<Vec<SessionSummary>, Status> {
+        let now = now_ms();
+        let mut summaries = Vec::new();
+        for (_, rec) in self
+            .store
+            .sessions()
+            .map_err(|e| Status::internal(e.to_string()))?
+        {
+            let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default())
+                .catch_()
+                .map_err(|e| Status::internal(e.to_string()))?;
+            if !rec.is_expired(now) {
+                summaries.push(self.summarize(rec)?);
+            }
+        }
+        Ok(summaries)
+    }
+
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Sessions not expired yet, that are still waiting for players or running.
+    pub fn unfinished_sessions(&self) -> Resultat<usize> {
+        let now = now_ms();
+        let mut count = 0;
+        for (_, rec) in self.store.sessions().catch_()? {
+            let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default()).catch_()?;
+            if matches!(
+                rec.state_at(now),
+                SessionState::Created | SessionState::Running
+            ) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    pub fn session_summary(&self, sid: &str) -> Result<SessionSummary, Status> {
+        let rec = self.load_session(sid)?;
+        self.summarize(rec)
+    }
+
+    /// Remove the session and its messages, whatever its state.
+    /// Those waiting in the session are woken up, and fail for the session is gone.
+    pub fn purge_session(&self, sid: &str) -> Result<(), Status> {
+        validate_session_id(sid)?;
+        let _guard = self.rec_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let rec = self
+            .store
+            .get_session(sid)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("Session {} does not exist", sid)))?;
+        let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default())
+            .catch_()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.store
+            .remove_session(sid)
+            .and_then(|_| self.store.remove_prefix(&rec.handle))
+            .and_then(|_| self.store.flush())
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.usage.release(&rec.handle);
+        if let Some(entry) = self.notifiers.remove(&rec.handle) {
+            entry.value().notify_waiters();
+        }
+        info!(session_id = %sid, "session purged");
+        self.audit
+            .record_or_log("session_purged", sid, "", json!({}));
+        Ok(())
+    }
+
+    /// Wait until all the indexed messages arrive, then return them.
+    /// Give up when any of the sessions expires.
+    /// Point-to-point messages are only readable by the addressee.
+    async fn wait(&self, idxs: &[Message], caller: &Caller) -> Result<Vec<Message>, Status> {
+        self.usage.admit_batch(idxs.len())?;
+        let recs = self.live_sessions(idxs)?;
+        let mut names = HashMap::new();
+        for idx in idxs.iter() {
+            let grant = self.authorize_audited(&recs[&idx.session_id], caller)?;
+            names.insert(idx.session_id.clone(), grant.name.clone());
+            if idx.dst != 0 && !grant.indices.contains(&idx.dst) {
+                let status = Status::permission_denied(format!(
+                    "Player {:?} cannot receive messages to index {}",
+                    &grant.name, idx.dst
+                ));
+                return Err(self.denied(&idx.session_id, &grant.name, caller, status));
+            }
+        }
+        for (sid, name) in names.iter() {
+            self.note_joined(&recs[sid], name, caller).await?;
+        }
+        let _waiter = self.usage.admit_waiter(&caller.conn)?;
+        let mut keys = Vec::with_capacity(idxs.len());
+        for idx in idxs.iter() {
+            let handle = &recs[&idx.session_id].handle;
+            let key = primary_key(handle, &idx.topic, idx.src, idx.dst, idx.seq)
+                .catch_()
+                .map_err(|e| Status::internal(e.to_string()))?;
+            keys.push(key);
+        }
+        let since = now_ms();
+        let waiting = idxs
+            .iter()
+            .zip(keys.iter())
+            .map(|(idx, key)| {
+                let msg = WaitingMessage {
+                    topic: idx.topic.clone(),
+                    src: idx.src,
+                    dst: idx.dst,
+                    seq: idx.seq,
+                    waiter: names[&idx.session_id].clone(),
+                    conn: caller.conn.clone(),
+                    since,
+                };
+                (*key, msg)
+            })
+            .collect();
+        let _entry = self.waiters.register(waiting);
+        let expire_at = recs.values().map(|rec| rec.expire_at).min().unwrap_or(0);
+
+        // Wait for all the requested messages at once.
+        let mut objs: Vec<Option<Vec<u8>>> = vec![None; idxs.len()];
+        loop {
+            // Also woken up by `abort_session`.
+            for sid in recs.keys() {
+                self.live_session(sid)?;
+            }
+            let mut missing = None;
+            for (key, obj) in keys.iter().zip(objs.iter_mut()) {
+                if obj.is_none() {
+                    *obj = self
+                        .store
+                        .get(key)
+                        .map_err(|e| Status::internal(e.to_string()))?;
+                    if obj.is_none() && missing.is_none() {
+                        missing = Some(*key);
+                    }
                 }
             }
-            sleep(Duration::from_secs(60)).await;
+            let Some(key) = missing else {
+                break;
+            };
+
+            // Subscribe before looking into the store again,
+            // so that an insertion in between is not missed.
+            let mut handle = [0u8; 16];
+            handle.copy_from_slice(&key[..16]);
+            let notify = self.notifier(&handle);
+            let notified = notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            let arrived = self
+                .store
+                .get(&key)
+                .map_err(|e| Status::internal(e.to_string()))?
+                .is_some();
+            if !arrived {
+                let remaining = Duration::from_millis(expire_at.saturating_sub(now_ms()));
+                if timeout(remaining, notified).await.is_err() {
+                    return Err(Status::deadline_exceeded("Session has expired"));
+                }
+            }
+        }
+
+        let mut msgs = Vec::with_capacity(idxs.len());
+        for (idx, obj) in idxs.iter().zip(objs) {
+            msgs.push(Message {
+                session_id: idx.session_id.clone(),
+                topic: idx.topic.clone(),
+                src: idx.src,
+                dst: idx.dst,
+                seq: idx.seq,
+                obj,
+            })
         }
+        Ok(msgs)
     }
 }
 
 #[tonic::async_trait]
 impl MpcSessionManager for Sesman {
+    #[instrument(
+        skip_all,
+        fields(op = "NewSession", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
     async fn new_session(
         &self,
         request: Request<SessionConfig>,
     ) -> Result<Response<SessionId>, Status> {
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(Status::unavailable(
+                "svarog_sesman is draining and accepts no new session",
+            ));
+        }
+        let conn = remote_conn(&request);
         let mut cfg = request.into_inner();
-        if cfg.session_id == "" {
+        if cfg.session_id.is_empty() {
             cfg.session_id = hex::encode(uuid::Uuid::now_v7().as_bytes()).to_lowercase();
         }
+        validate_session_id(&cfg.session_id)?;
+        Span::current().record("session_id", cfg.session_id.as_str());
+        if cfg.ttl == 0 {
+            cfg.ttl = self.settings.default_ttl;
+        }
+        if cfg.ttl > self.settings.max_ttl {
+            let status = Status::invalid_argument(format!(
+                "ttl {}s exceeds the maximum {}s",
+                cfg.ttl, self.settings.max_ttl
+            ));
+            return Err(self.rejected(&cfg.session_id, status));
+        }
+        cfg.ttl_remaining = 0;
+        if !cfg.player_certs.is_empty() && !self.settings.mtls {
+            let status = Status::failed_precondition(
+                "Binding players to certificates requires sesman to run with mTLS",
+            );
+            return Err(self.rejected(&cfg.session_id, status));
+        }
+        if let Err(status) = self.check_config(&cfg) {
+            return Err(self.rejected(&cfg.session_id, status));
+        }
 
-        let key = primary_key(&cfg.session_id, "session config", 0, 0, 0)
+        let mut rec = SessionRecord::new(cfg)
             .catch_()
             .map_err(|e| Status::internal(e.to_string()))?;
-        let val = serde_pickle::to_vec(&cfg, Default::default())
+        let names = rec.name_indices();
+        for (name, binding) in rec.cfg.player_certs.iter() {
+            if !names.contains_key(name) {
+                let status = Status::invalid_argument(format!(
+                    "Certificate is bound to unknown player {:?}",
+                    name
+                ));
+                return Err(self.rejected(&rec.cfg.session_id, status));
+            }
+            if binding.subject.is_empty() && binding.spki_sha256.is_empty() {
+                let status = Status::invalid_argument(format!(
+                    "Certificate binding of player {:?} is empty",
+                    name
+                ));
+                return Err(self.rejected(&rec.cfg.session_id, status));
+            }
+        }
+        let tokens = rec
+            .issue_tokens()
             .catch_()
             .map_err(|e| Status::internal(e.to_string()))?;
-        self.0.compare_insert(key, val, |_| true);
+        // Checked and saved under the lock, so that two sessions with the same id
+        // cannot both be created.
+        {
+            let _guard = self.rec_lock.lock().unwrap_or_else(|e| e.into_inner());
+            match self.load_session(&rec.cfg.session_id) {
+                Ok(_) => {
+                    let status = Status::already_exists(format!(
+                        "Session {} already exists",
+                        &rec.cfg.session_id
+                    ));
+                    return Err(self.rejected(&rec.cfg.session_id, status));
+                }
+                Err(status)
+                    if [Code::NotFound, Code::DeadlineExceeded].contains(&status.code()) => {}
+                Err(status) => return Err(status),
+            }
+            self.usage.admit_session(&rec.handle)?;
+            if let Err(status) = self.save_session(&rec) {
+                self.usage.release(&rec.handle);
+                return Err(status);
+            }
+        }
+        // Audited once saved, and removed again if it cannot be audited,
+        // so that every session created is audited, and no other. Nobody
+        // holds its tokens in the meantime.
+        let detail = json!({
+            "config_sha256": config_digest(&rec.cfg).map_err(|e| Status::internal(e.to_string()))?,
+            "kind": rec.cfg.kind().as_str_name(),
+            "threshold": rec.cfg.threshold,
+            "players": &rec.cfg.players,
+            "players_reshared": &rec.cfg.players_reshared,
+            "ttl": rec.cfg.ttl,
+            "conn": conn,
+        });
+        if let Err(status) = self
+            .audit_record("session_created", &rec.cfg.session_id, "", detail)
+            .await
+        {
+            let _guard = self.rec_lock.lock().unwrap_or_else(|e| e.into_inner());
+            self.store
+                .remove_session(&rec.cfg.session_id)
+                .and_then(|_| self.store.flush())
+                .map_err(|e| Status::internal(e.to_string()))?;
+            self.usage.release(&rec.handle);
+            return Err(status);
+        }
+        self.metrics.sessions_created.inc();
+        info!(
+            ttl = rec.cfg.ttl,
+            players = rec.participants().len(),
+            "session created"
+        );
 
         let sid = SessionId {
-            value: cfg.session_id.clone(),
+            value: rec.cfg.session_id.clone(),
+            tokens,
         };
 
         Ok(Response::new(sid))
     }
 
+    #[instrument(
+        skip_all,
+        fields(op = "GetSessionConfig", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
     async fn get_session_config(
         &self,
         request: Request<SessionId>,
     ) -> Result<Response<SessionConfig>, Status> {
         let sid = request.into_inner().value;
-        let key = primary_key(&sid, "session config", 0, 0, 0)
-            .catch_()
-            .map_err(|e| Status::internal(e.to_string()))?;
-        let entry = self
-            .0
-            .get(&key)
-            .ifnone_()
-            .map_err(|e| Status::internal(e.to_string()))?;
-        let val = entry.value();
-        let cfg: SessionConfig = serde_pickle::from_slice(val, Default::default())
-            .catch_()
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let rec = self.live_session(&sid)?;
+        let mut cfg = rec.cfg.clone();
+        cfg.ttl_remaining = rec.remaining_ms(now_ms()).div_ceil(1000);
         Ok(Response::new(cfg))
     }
 
+    #[instrument(
+        skip_all,
+        fields(op = "Inbox", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
     async fn inbox(&self, req: Request<VecMessage>) -> Result<Response<Void>, Status> {
+        let caller = Caller::of_request(&req)?;
         let msgs = req.into_inner().values;
-        for msg in msgs.iter() {
-            let key = primary_key(&msg.session_id, &msg.topic, msg.src, msg.dst, msg.seq)
-                .catch_()
-                .map_err(|e| Status::internal(e.to_string()))?;
-            let val = msg
-                .obj
-                .as_ref()
-                .ifnone_()
-                .map_err(|e| Status::internal(e.to_string()))?
-                .clone();
-            let _ = self.0.compare_insert(key, val, |_| true);
-        }
+        self.post(&msgs, &caller).await?;
+        self.metrics.observe_msgs("Inbox", "in", &msgs);
         Ok(Response::new(Void {}))
     }
 
+    #[instrument(
+        skip_all,
+        fields(op = "Outbox", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
     async fn outbox(&self, request: Request<VecMessage>) -> Result<Response<VecMessage>, Status> {
+        let caller = Caller::of_request(&request)?;
         let idxs = request.into_inner().values;
-        let mut resp = Vec::new();
-        for idx in idxs.iter() {
-            let key = primary_key(&idx.session_id, &idx.topic, idx.src, idx.dst, idx.seq)
-                .catch_()
-                .map_err(|e| Status::internal(e.to_string()))?;
-            let obj = loop {
-                let entry = self.0.get(&key);
-                match entry {
-                    Some(ref_obj) => break ref_obj.value().clone(),
-                    None => {
-                        sleep(Duration::from_secs(1)).await;
-                        continue;
+        let msgs = self.timed_wait("Outbox", &idxs, &caller).await?;
+        Ok(Response::new(VecMessage { values: msgs }))
+    }
+
+    type ExchangeStream = ReceiverStream<Result<ExchangeReply, Status>>;
+
+    #[instrument(
+        skip_all,
+        fields(op = "Exchange", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn exchange(
+        &self,
+        request: Request<Streaming<Message>>,
+    ) -> Result<Response<Self::ExchangeStream>, Status> {
+        let caller = Caller::of_request(&request)?;
+        let mut incoming = request.into_inner();
+        let (tx, rx) = mpsc::channel(EXCHANGE_BUFFER);
+        let sesman = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let mut msg = match incoming.message().await {
+                    Ok(Some(msg)) => msg,
+                    Ok(None) => break,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
                     }
                 };
-            };
-            resp.push(Message {
-                session_id: idx.session_id.clone(),
-                topic: idx.topic.clone(),
-                src: idx.src,
-                dst: idx.dst,
-                seq: idx.seq,
-                obj: Some(obj),
-            })
+                if msg.obj.is_some() {
+                    let res = sesman.post(std::slice::from_ref(&msg), &caller).await;
+                    match res.as_ref() {
+                        Ok(()) => sesman.metrics.observe_msgs(
+                            "Exchange",
+                            "in",
+                            std::slice::from_ref(&msg),
+                        ),
+                        Err(status) => warn!(code = ?status.code(), "{}", status.message()),
+                    }
+                    // Acknowledge with the index only.
+                    msg.obj = None;
+                    let _ = tx.send(Ok(exchange_reply(msg, res.err()))).await;
+                    continue;
+                }
+
+                // A request. Send the message back once it arrives.
+                let sesman = sesman.clone();
+                let tx = tx.clone();
+                let caller = caller.clone();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        res = sesman.timed_wait("Exchange", std::slice::from_ref(&msg), &caller) => {
+                            match res {
+                                Ok(msgs) => {
+                                    for msg in msgs {
+                                        let _ = tx.send(Ok(exchange_reply(msg, None))).await;
+                                    }
+                                }
+                                Err(status) => {
+                                    warn!(code = ?status.code(), "{}", status.message());
+                                    let _ = tx.send(Ok(exchange_reply(msg, Some(status)))).await;
+                                }
+                            }
+                        }
+                        _ = tx.closed() => {}
+                    }
+                }.in_current_span());
+            }
+        }.in_current_span());
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "JoinSession", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn join_session(
+        &self,
+        request: Request<SessionId>,
+    ) -> Result<Response<JoinReply>, Status> {
+        let caller = Caller::of_request(&request)?;
+        let sid = request.into_inner().value;
+        let rec = self.live_session(&sid)?;
+        let player = self.authorize_audited(&rec, &caller)?.name.clone();
+        self.note_joined(&rec, &player, &caller).await?;
+        info!("joined");
+        Ok(Response::new(JoinReply { player }))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "CompleteSession", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
+    #[allow(clippy::result_large_err)]
+    async fn complete_session(
+        &self,
+        request: Request<SessionId>,
+    ) -> Result<Response<Void>, Status> {
+        let caller = Caller::of_request(&request)?;
+        let sid = request.into_inner().value;
+        let rec = self.live_session(&sid)?;
+        let name = self.authorize_audited(&rec, &caller)?.name.clone();
+        self.note_joined(&rec, &name, &caller).await?;
+        let _audit_guard = self.audit_lock.lock().await;
+        // Completed on a copy first, to audit what saving it will do.
+        let mut rec = self.live_session(&sid)?;
+        if !rec.completed.contains_key(&name) {
+            rec.complete(&name, now_ms());
+            self.audit_record("player_completed", &sid, &name, json!({}))
+                .await?;
+            if rec.state == SessionState::Completed {
+                let detail = json!({ "completed": rec.completed.keys().collect::<Vec<_>>() });
+                self.audit_record("session_completed", &sid, "", detail)
+                    .await?;
+            }
+        }
+        let rec = self.update_session(&sid, |rec| {
+            rec.complete(&name, now_ms());
+            Ok(())
+        })?;
+        info!(state = ?rec.state, "completed");
+        if rec.state == SessionState::Completed {
+            self.notifier(&rec.handle).notify_waiters();
         }
+        Ok(Response::new(Void {}))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "AbortSession", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
+    #[allow(clippy::result_large_err)]
+    async fn abort_session(
+        &self,
+        request: Request<AbortRequest>,
+    ) -> Result<Response<Void>, Status> {
+        let caller = Caller::of_request(&request)?;
+        let req = request.into_inner();
+        let rec = self.live_session(&req.session_id)?;
+        let name = self.authorize_audited(&rec, &caller)?.name.clone();
+        self.note_joined(&rec, &name, &caller).await?;
+        let _audit_guard = self.audit_lock.lock().await;
+        self.live_session(&req.session_id)?;
+        let detail = json!({ "reason": &req.reason });
+        self.audit_record("session_aborted", &req.session_id, &name, detail)
+            .await?;
+        let rec = self.update_session(&req.session_id, |rec| {
+            rec.abort(&req.reason, now_ms());
+            Ok(())
+        })?;
+        info!(reason = %req.reason, "aborted");
+        self.notifier(&rec.handle).notify_waiters();
+        Ok(Response::new(Void {}))
+    }
+
+    #[instrument(
+        skip_all,
+        fields(op = "GetEquivocations", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn get_equivocations(
+        &self,
+        request: Request<SessionId>,
+    ) -> Result<Response<VecEquivocation>, Status> {
+        let caller = Caller::of_request(&request)?;
+        let sid = request.into_inner().value;
+        let rec = self.load_session(&sid)?;
+        self.authorize_audited(&rec, &caller)?;
+        Ok(Response::new(VecEquivocation {
+            values: rec.equivocations,
+        }))
+    }
 
-        Ok(Response::new(VecMessage { values: resp }))
+    #[instrument(
+        skip_all,
+        fields(op = "GetSessionStatus", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
+    async fn get_session_status(
+        &self,
+        request: Request<SessionId>,
+    ) -> Result<Response<SessionStatus>, Status> {
+        let caller = Caller::of_request(&request)?;
+        let sid = request.into_inner().value;
+        let rec = self.load_session(&sid)?;
+        self.authorize_audited(&rec, &caller)?;
+        let posted = self.posted_messages(&rec.handle)?;
+        let waiting = self.waiting_messages(&rec.handle)?;
+        Ok(Response::new(SessionStatus {
+            session_id: sid,
+            posted,
+            waiting,
+            state: rec.state_at(now_ms()) as i32,
+            joined: rec.joined.into_iter().collect(),
+            completed: rec.completed.into_iter().collect(),
+            created_at: rec.created_at,
+            finished_at: rec.finished_at,
+        }))
     }
 
+    #[instrument(
+        skip_all,
+        fields(op = "Ping", session_id = Empty, player = Empty),
+        err(level = Level::WARN)
+    )]
     async fn ping(&self, _: Request<Void>) -> Result<Response<EchoMessage>, Status> {
         Ok(Response::new(EchoMessage {
             value: "Svarog Session Manager is running.".to_owned(),
         }))
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use erreur::*;
+    use mpc_sig_abs::BatchMessenger;
+    use svarog_grpc::{
+        mpc_session_manager_server::{MpcSessionManager, MpcSessionManagerServer},
+        AbortRequest, CertBinding, Limits, Message, SessionConfig, SessionId, SessionKind,
+        SessionState, VecMessage,
+    };
+    use svarog_sesman::{AuditRecord, SvarogChannel};
+    use tokio::{
+        net::TcpListener,
+        time::{sleep, timeout, Duration},
+    };
+    use tonic::{
+        transport::{server::TcpIncoming, Server},
+        Code, Request, Status,
+    };
+
+    use crate::{
+        server_cert::PeerCert, server_session::SessionRecord, server_storage::MemStore, *,
+    };
+
+    pub(crate) fn settings() -> Settings {
+        Settings {
+            default_ttl: 60,
+            max_ttl: 3600,
+            recycle_interval: 3600,
+            mtls: false,
+            public_urls: Vec::new(),
+            limits: Limits::default(),
+        }
+    }
+
+    pub(crate) async fn sesman(settings: Settings) -> Resultat<Sesman> {
+        let store = Arc::new(MemStore::default());
+        let (sesman, _) = Sesman::init(settings, store, Arc::default())
+            .await
+            .catch_()?;
+        Ok(sesman)
+    }
+
+    /// Serve `sesman` over plain HTTP on a free port. Return its URL.
+    pub(crate) async fn serve(sesman: Sesman) -> Resultat<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.catch_()?;
+        let url = format!("http://{}", listener.local_addr().catch_()?);
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .ok()
+            .ifnone("", "Failed to listen")?;
+        let router = Server::builder().add_service(MpcSessionManagerServer::new(sesman));
+        tokio::spawn(router.serve_with_incoming(incoming));
+        Ok(url)
+    }
+
+    /// A config of the attending players.
+    pub(crate) fn players(names: &[&str]) -> SessionConfig {
+        SessionConfig {
+            players: names.iter().map(|name| (name.to_string(), true)).collect(),
+            threshold: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exchange_acks_posts() -> Resultat<()> {
+        let url = serve(sesman(settings()).await?).await?;
+        let sid = SvarogChannel::new_session(&players(&["A", "B"]), &url, false).await?;
+        let (mut a, _) = SvarogChannel::use_session(&sid.value, &sid.tokens["A"], &url, false)
+            .await
+            .catch_()?;
+        let (mut b, _) = SvarogChannel::use_session(&sid.value, &sid.tokens["B"], &url, false)
+            .await
+            .catch_()?;
+
+        a.register_send("t", 1, 2, 0, &"x".to_owned())?;
+        a.register_send("t", 1, 0, 0, &"y".to_owned())?;
+        a.execute_send().await?;
+        // Rejected at once: B may not send as index 1.
+        b.register_send("t", 1, 0, 1, &"z".to_owned())?;
+        assert_throw!(b.execute_send().await.is_err());
+        // The stream of B goes on after the failed post.
+        b.register_send("t", 2, 0, 0, &"w".to_owned())?;
+        b.execute_send().await?;
+        b.register_receive("t", 1, 2, 0)?;
+        b.register_receive("t", 1, 0, 0)?;
+        b.execute_receive().await?;
+        assert_throw!(b.unpack_receive::<String>("t", 1, 2, 0)? == "x");
+        assert_throw!(b.unpack_receive::<String>("t", 1, 0, 0)? == "y");
+        a.register_receive("t", 2, 0, 0)?;
+        a.execute_receive().await?;
+        assert_throw!(a.unpack_receive::<String>("t", 2, 0, 0)? == "w");
+        Ok(())
+    }
+
+    /// `msg` sent with `token`.
+    pub(crate) fn request<T>(msg: T, token: &str) -> Request<T> {
+        let mut req = Request::new(msg);
+        let auth = format!("Bearer {}", token).parse().unwrap();
+        req.metadata_mut().insert("authorization", auth);
+        req
+    }
+
+    #[tokio::test]
+    async fn test_outbox_wakes_on_post() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst: 2,
+            seq: 0,
+            obj: Some(b"x".to_vec()),
+        };
+        let idx = Message {
+            obj: None,
+            ..msg.clone()
+        };
+        let waiter = {
+            let sesman = sesman.clone();
+            let req = request(VecMessage { values: vec![idx] }, &sid.tokens["B"]);
+            tokio::spawn(async move { sesman.outbox(req).await })
+        };
+        sleep(Duration::from_millis(100)).await;
+        assert_throw!(!waiter.is_finished());
+        sesman
+            .inbox(request(VecMessage { values: vec![msg] }, &sid.tokens["A"]))
+            .await
+            .catch_()?;
+        // Well before the next tick of a one-second poll.
+        let msgs = timeout(Duration::from_millis(300), waiter)
+            .await
+            .catch("", "Outbox not woken up by the post")?
+            .catch_()?
+            .catch_()?
+            .into_inner()
+            .values;
+        assert_throw!(msgs.len() == 1 && msgs[0].obj.as_deref() == Some(&b"x"[..]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_new_session_checks_threshold() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let with = |kind: SessionKind, threshold| SessionConfig {
+            kind: kind as i32,
+            threshold,
+            ..players(&["A", "B"])
+        };
+        for (kind, threshold, ok) in [
+            (SessionKind::Unspecified, 0, false),
+            (SessionKind::Unspecified, 2, true),
+            (SessionKind::Unspecified, 3, false),
+            (SessionKind::Keygen, 1, true),
+            (SessionKind::Keygen, 2, false),
+        ] {
+            let res = sesman
+                .new_session(Request::new(with(kind, threshold)))
+                .await;
+            let code = res.err().map(|status| status.code());
+            assert_throw!(
+                code == (!ok).then_some(Code::InvalidArgument),
+                format!("{} {}", kind.as_str_name(), threshold)
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ttl() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let with_ttl = |ttl| SessionConfig {
+            ttl,
+            ..players(&["A", "B"])
+        };
+        let res = sesman.new_session(Request::new(with_ttl(3601))).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::InvalidArgument));
+
+        let long = sesman
+            .new_session(Request::new(with_ttl(0)))
+            .await
+            .catch_()?
+            .into_inner();
+        let cfg = sesman
+            .get_session_config(Request::new(long.clone()))
+            .await
+            .catch_()?
+            .into_inner();
+        assert_throw!(cfg.ttl == 60 && (59..=60).contains(&cfg.ttl_remaining));
+
+        let short = sesman
+            .new_session(Request::new(with_ttl(1)))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = Message {
+            session_id: short.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst: 0,
+            seq: 0,
+            obj: Some(b"x".to_vec()),
+        };
+        let idx = Message {
+            src: 2,
+            obj: None,
+            ..msg.clone()
+        };
+        let token = &short.tokens["A"];
+        sesman
+            .inbox(request(VecMessage { values: vec![msg] }, token))
+            .await
+            .catch_()?;
+        // Waits no longer than the session lives.
+        let req = request(VecMessage { values: vec![idx] }, token);
+        let res = timeout(Duration::from_secs(3), sesman.outbox(req))
+            .await
+            .catch_()?;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::DeadlineExceeded));
+        let res = sesman.get_session_config(Request::new(short.clone())).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::DeadlineExceeded));
+
+        let rec = sesman.store.get_session(&short.value)?.ifnone_()?;
+        let rec: SessionRecord = serde_pickle::from_slice(&rec, Default::default()).catch_()?;
+        let key = primary_key(&rec.handle, "t", 1, 0, 0)?;
+        assert_throw!(sesman.store.get(&key)?.is_some());
+        let usage = sesman.usage();
+        assert_throw!((usage.sessions, usage.messages) == (2, 1));
+        sesman.recycle_once()?;
+        let usage = sesman.usage();
+        assert_throw!((usage.sessions, usage.messages) == (1, 0));
+        assert_throw!(sesman.store.get(&key)?.is_none());
+        let res = sesman.get_session_config(Request::new(short.clone())).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::NotFound));
+        sesman
+            .get_session_config(Request::new(long.clone()))
+            .await
+            .catch_()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_session_id() -> Resultat<()> {
+        for sid in ["a", "order-2024_01.02:3", &"x".repeat(MAX_SESSION_ID_LEN)] {
+            assert_throw!(validate_session_id(sid).is_ok(), sid);
+        }
+        let too_long = "x".repeat(MAX_SESSION_ID_LEN + 1);
+        for sid in ["", too_long.as_str(), "a/b", "a b", "订单", "a\n"] {
+            let code = validate_session_id(sid).err().map(|status| status.code());
+            assert_throw!(code == Some(Code::InvalidArgument), sid);
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_new_session_id_taken_once() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let mut cfg = players(&["A", "B"]);
+        cfg.session_id = "order-1".to_owned();
+        let calls: Vec<_> = (0..8)
+            .map(|_| {
+                let sesman = sesman.clone();
+                let cfg = cfg.clone();
+                tokio::spawn(async move { sesman.new_session(Request::new(cfg)).await })
+            })
+            .collect();
+        let mut created = Vec::new();
+        for call in calls {
+            match call.await.catch_()? {
+                Ok(resp) => created.push(resp.into_inner()),
+                Err(status) => assert_throw!(status.code() == Code::AlreadyExists),
+            }
+        }
+        assert_throw!(created.len() == 1);
+        // The tokens handed out are those of the kept session.
+        let sid = SessionId {
+            value: "order-1".to_owned(),
+            tokens: Default::default(),
+        };
+        sesman
+            .join_session(request(sid, &created[0].tokens["A"]))
+            .await
+            .catch_()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_abort_fails_waiters() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let idx = Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst: 2,
+            seq: 0,
+            obj: None,
+        };
+        let waiter = {
+            let sesman = sesman.clone();
+            let req = request(
+                VecMessage {
+                    values: vec![idx.clone()],
+                },
+                &sid.tokens["B"],
+            );
+            tokio::spawn(async move { sesman.outbox(req).await })
+        };
+        sleep(Duration::from_millis(100)).await;
+        let abort = AbortRequest {
+            session_id: sid.value.clone(),
+            reason: "bad share".to_owned(),
+        };
+        sesman
+            .abort_session(request(abort, &sid.tokens["A"]))
+            .await
+            .catch_()?;
+        let res = timeout(Duration::from_millis(300), waiter)
+            .await
+            .catch("", "Outbox not woken up by the abort")?
+            .catch_()?;
+        let status = res.err().ifnone("", "Outbox succeeded after the abort")?;
+        assert_throw!(status.code() == Code::Aborted && status.message().contains("bad share"));
+
+        let msg = Message {
+            obj: Some(b"x".to_vec()),
+            ..idx
+        };
+        let res = sesman
+            .inbox(request(VecMessage { values: vec![msg] }, &sid.tokens["A"]))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::Aborted));
+        let session_id = SessionId {
+            value: sid.value.clone(),
+            ..Default::default()
+        };
+        let status = sesman
+            .get_session_status(request(session_id, &sid.tokens["B"]))
+            .await
+            .catch_()?
+            .into_inner();
+        assert_throw!(status.state() == SessionState::Aborted && status.finished_at > 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cert_binding() -> Resultat<()> {
+        let binding = CertBinding {
+            subject: "CN=Alice".to_owned(),
+            ..Default::default()
+        };
+        let cfg = SessionConfig {
+            player_certs: [("A".to_owned(), binding.clone())].into(),
+            ..players(&["A", "B"])
+        };
+        let res = sesman(settings())
+            .await?
+            .new_session(Request::new(cfg.clone()))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::FailedPrecondition));
+
+        let mut settings = settings();
+        settings.mtls = true;
+        let sesman = sesman(settings).await?;
+        for (name, binding) in [("D", binding), ("A", CertBinding::default())] {
+            let invalid = SessionConfig {
+                player_certs: [(name.to_owned(), binding)].into(),
+                ..cfg.clone()
+            };
+            let res = sesman.new_session(Request::new(invalid)).await;
+            assert_throw!(res.err().map(|status| status.code()) == Some(Code::InvalidArgument));
+        }
+        let sid = sesman
+            .new_session(Request::new(cfg))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = |src| Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src,
+            dst: 0,
+            seq: 0,
+            obj: Some(vec![src as u8]),
+        };
+        let caller = |name: &str, subject: Option<&str>| Caller {
+            token: sid.tokens[name].clone(),
+            cert: subject.map(|subject| PeerCert {
+                subject: subject.to_owned(),
+                spki_sha256: "00".to_owned(),
+            }),
+            conn: String::new(),
+        };
+        let code = |res: Result<(), Status>| res.err().map(|status| status.code());
+        let res = sesman.post(&[msg(1)], &caller("A", None)).await;
+        assert_throw!(code(res) == Some(Code::Unauthenticated));
+        let res = sesman
+            .post(&[msg(1)], &caller("A", Some("CN=Mallory")))
+            .await;
+        assert_throw!(code(res) == Some(Code::PermissionDenied));
+        sesman
+            .post(&[msg(1)], &caller("A", Some("CN=Alice")))
+            .await
+            .catch_()?;
+        // B is not bound to any certificate.
+        sesman.post(&[msg(2)], &caller("B", None)).await.catch_()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sesman_url_in_public_urls() -> Resultat<()> {
+        let with_url = |url: &str| SessionConfig {
+            sesman_url: url.to_owned(),
+            ..players(&["A"])
+        };
+        // Served over plain HTTP behind a proxy terminating TLS.
+        let proxied = sesman(settings()).await?;
+        for url in ["https://sesman.example", "http://127.0.0.1:2000"] {
+            let res = proxied.new_session(Request::new(with_url(url))).await;
+            assert_throw!(res.is_ok(), url);
+        }
+
+        let mut settings = settings();
+        settings.public_urls = vec!["https://sesman.example".to_owned()];
+        let listed = sesman(settings).await?;
+        for url in ["https://sesman.example", "https://sesman.example/", ""] {
+            let res = listed.new_session(Request::new(with_url(url))).await;
+            assert_throw!(res.is_ok(), url);
+        }
+        for url in ["http://sesman.example", "https://other.example"] {
+            let res = listed.new_session(Request::new(with_url(url))).await;
+            let code = res.err().map(|status| status.code());
+            assert_throw!(code == Some(Code::InvalidArgument), url);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_only_provider_sends_as_index_0() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = |src| VecMessage {
+            values: vec![Message {
+                session_id: sid.value.clone(),
+                topic: "mnem".to_owned(),
+                src,
+                dst: 1,
+                seq: 0,
+                obj: Some(vec![src as u8]),
+            }],
+        };
+        let res = sesman.inbox(request(msg(0), &sid.tokens["A"])).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::PermissionDenied));
+        sesman
+            .inbox(request(msg(0), &sid.tokens[""]))
+            .await
+            .catch_()?;
+        let res = sesman.inbox(request(msg(1), &sid.tokens[""])).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::PermissionDenied));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retries_keep_quota() -> Resultat<()> {
+        let mut settings = settings();
+        settings.limits.max_msgs_per_session = 2;
+        let sesman = sesman(settings).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let token = &sid.tokens["A"];
+        let msg = |seq, obj: &[u8]| VecMessage {
+            values: vec![Message {
+                session_id: sid.value.clone(),
+                topic: "t".to_owned(),
+                src: 1,
+                dst: 0,
+                seq,
+                obj: Some(obj.to_vec()),
+            }],
+        };
+        sesman.inbox(request(msg(0, b"x"), token)).await.catch_()?;
+        // Neither a re-send nor an equivocation is counted.
+        for _ in 0..3 {
+            sesman.inbox(request(msg(0, b"x"), token)).await.catch_()?;
+            let res = sesman.inbox(request(msg(0, b"y"), token)).await;
+            assert_throw!(res.err().map(|status| status.code()) == Some(Code::AlreadyExists));
+        }
+        assert_throw!(sesman.usage().messages == 1);
+        sesman.inbox(request(msg(1, b"x"), token)).await.catch_()?;
+        let res = sesman.inbox(request(msg(2, b"x"), token)).await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::ResourceExhausted));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_posts_counted_once() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A"])))
+            .await
+            .catch_()?
+            .into_inner();
+        // Large batches, so that the posts overlap between checking and storing.
+        let values: Vec<Message> = (0..500)
+            .map(|seq| Message {
+                session_id: sid.value.clone(),
+                topic: "t".to_owned(),
+                src: 1,
+                dst: 0,
+                seq,
+                obj: Some(b"xy".to_vec()),
+            })
+            .collect();
+        let posts: Vec<_> = (0..8)
+            .map(|_| {
+                let sesman = sesman.clone();
+                let req = request(
+                    VecMessage {
+                        values: values.clone(),
+                    },
+                    &sid.tokens["A"],
+                );
+                tokio::spawn(async move { sesman.inbox(req).await })
+            })
+            .collect();
+        for post in posts {
+            post.await.catch_()?.catch_()?;
+        }
+        let usage = sesman.usage();
+        assert_throw!(usage.messages == 500 && usage.bytes == 1000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_equivocations() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = |obj: &[u8]| Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst: 0,
+            seq: 0,
+            obj: Some(obj.to_vec()),
+        };
+        let post = |obj: &[u8]| {
+            let req = request(
+                VecMessage {
+                    values: vec![msg(obj)],
+                },
+                &sid.tokens["A"],
+            );
+            sesman.inbox(req)
+        };
+        post(b"x").await.catch_()?;
+        for obj in [b"y", b"z"] {
+            let res = post(obj).await;
+            assert_throw!(res.err().map(|status| status.code()) == Some(Code::AlreadyExists));
+        }
+        // The first payload is kept, and the slot is reported once.
+        let idx = Message {
+            obj: None,
+            ..msg(b"")
+        };
+        let msgs = sesman
+            .outbox(request(VecMessage { values: vec![idx] }, &sid.tokens["B"]))
+            .await
+            .catch_()?
+            .into_inner()
+            .values;
+        assert_throw!(msgs[0].obj.as_deref() == Some(&b"x"[..]));
+        let session_id = SessionId {
+            value: sid.value.clone(),
+            ..Default::default()
+        };
+        let equivs = sesman
+            .get_equivocations(request(session_id, &sid.tokens["B"]))
+            .await
+            .catch_()?
+            .into_inner()
+            .values;
+        assert_throw!(equivs.len() == 1);
+        let equiv = &equivs[0];
+        assert_throw!((equiv.topic.as_str(), equiv.src, equiv.dst, equiv.seq) == ("t", 1, 0, 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_status() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = |dst, seq| Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst,
+            seq,
+            obj: Some(b"xyz".to_vec()),
+        };
+        let post = |msg| sesman.inbox(request(VecMessage { values: vec![msg] }, &sid.tokens["A"]));
+        let status = || {
+            let session_id = SessionId {
+                value: sid.value.clone(),
+                ..Default::default()
+            };
+            sesman.get_session_status(request(session_id, &sid.tokens["A"]))
+        };
+        post(msg(2, 0)).await.catch_()?;
+        let waiter = {
+            let sesman = sesman.clone();
+            let idxs = [msg(2, 0), msg(0, 1)]
+                .into_iter()
+                .map(|msg| Message { obj: None, ..msg })
+                .collect();
+            let req = request(VecMessage { values: idxs }, &sid.tokens["B"]);
+            tokio::spawn(async move { sesman.outbox(req).await })
+        };
+        sleep(Duration::from_millis(100)).await;
+
+        let st = status().await.catch_()?.into_inner();
+        assert_throw!(st.posted.len() == 1);
+        let posted = &st.posted[0];
+        assert_throw!(
+            (posted.topic.as_str(), posted.src, posted.dst, posted.seq) == ("t", 1, 2, 0)
+        );
+        assert_throw!(posted.size == 3 && posted.arrived_at >= st.created_at);
+        // Only the message not posted yet.
+        assert_throw!(st.waiting.len() == 1);
+        let waiting = &st.waiting[0];
+        assert_throw!((waiting.src, waiting.dst, waiting.seq) == (1, 0, 1));
+        assert_throw!(waiting.waiter == "B" && waiting.since > 0);
+
+        post(msg(0, 1)).await.catch_()?;
+        waiter.await.catch_()?.catch_()?;
+        let st = status().await.catch_()?.into_inner();
+        assert_throw!(st.posted.len() == 2 && st.waiting.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let session_id = || SessionId {
+            value: sid.value.clone(),
+            ..Default::default()
+        };
+        let status = || async {
+            let st = sesman
+                .get_session_status(request(session_id(), &sid.tokens["A"]))
+                .await
+                .catch_()?
+                .into_inner();
+            Ok::<_, Box<Erreur>>(st)
+        };
+        assert_throw!(status().await?.state() == SessionState::Created);
+        sesman
+            .join_session(request(session_id(), &sid.tokens["A"]))
+            .await
+            .catch_()?;
+        assert_throw!(status().await?.state() == SessionState::Running);
+        // Completing twice is fine.
+        for _ in 0..2 {
+            sesman
+                .complete_session(request(session_id(), &sid.tokens["A"]))
+                .await
+                .catch_()?;
+        }
+        let st = status().await?;
+        assert_throw!(st.state() == SessionState::Running && st.finished_at == 0);
+        assert_throw!(st.completed.keys().collect::<Vec<_>>() == ["A"]);
+
+        // The mnemonic provider waits, but does not need to complete.
+        let idx = Message {
+            session_id: sid.value.clone(),
+            topic: "t".to_owned(),
+            src: 1,
+            dst: 0,
+            seq: 0,
+            obj: None,
+        };
+        let waiter = {
+            let sesman = sesman.clone();
+            let req = request(
+                VecMessage {
+                    values: vec![idx.clone()],
+                },
+                &sid.tokens[""],
+            );
+            tokio::spawn(async move { sesman.outbox(req).await })
+        };
+        sleep(Duration::from_millis(100)).await;
+        sesman
+            .complete_session(request(session_id(), &sid.tokens["B"]))
+            .await
+            .catch_()?;
+        let st = status().await?;
+        assert_throw!(st.state() == SessionState::Completed && st.finished_at > 0);
+        assert_throw!(st.joined.len() == 3 && st.completed.len() == 2);
+        let res = timeout(Duration::from_millis(300), waiter)
+            .await
+            .catch("", "Outbox not woken up by the completion")?
+            .catch_()?;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::FailedPrecondition));
+
+        let msg = Message {
+            obj: Some(b"x".to_vec()),
+            ..idx
+        };
+        let res = sesman
+            .inbox(request(VecMessage { values: vec![msg] }, &sid.tokens["A"]))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::FailedPrecondition));
+        let res = sesman
+            .join_session(request(session_id(), &sid.tokens["B"]))
+            .await;
+        assert_throw!(res.err().map(|status| status.code()) == Some(Code::FailedPrecondition));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_reports_need_token() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let other = sesman
+            .new_session(Request::new(players(&["A"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let req = || SessionId {
+            value: sid.value.clone(),
+            ..Default::default()
+        };
+        let unauthenticated = |code: Option<Code>| code == Some(Code::Unauthenticated);
+        for token in [None, Some(other.tokens["A"].as_str())] {
+            let with_token = |msg| match token {
+                Some(token) => request(msg, token),
+                None => Request::new(msg),
+            };
+            let res = sesman.get_session_status(with_token(req())).await;
+            assert_throw!(unauthenticated(res.err().map(|status| status.code())));
+            let res = sesman.get_equivocations(with_token(req())).await;
+            assert_throw!(unauthenticated(res.err().map(|status| status.code())));
+        }
+        for token in sid.tokens.values() {
+            sesman
+                .get_session_status(request(req(), token))
+                .await
+                .catch_()?;
+            sesman
+                .get_equivocations(request(req(), token))
+                .await
+                .catch_()?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spans_name_session_and_player() -> Resultat<()> {
+        #[derive(Clone, Default)]
+        struct Buf(Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Buf {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let buf = Buf::default();
+        let subscriber = {
+            let buf = buf.clone();
+            tracing_subscriber::fmt()
+                .json()
+                .with_current_span(true)
+                .with_span_list(false)
+                .with_max_level(tracing::Level::DEBUG)
+                .with_writer(move || buf.clone())
+                .finish()
+        };
+        // The test runs on a single thread, along with the tasks it spawns.
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let msg = |obj: &str| VecMessage {
+            values: vec![Message {
+                session_id: sid.value.clone(),
+                topic: "t".to_owned(),
+                src: 1,
+                dst: 0,
+                seq: 0,
+                obj: Some(obj.as_bytes().to_vec()),
+            }],
+        };
+        sesman
+            .inbox(request(msg("first-secret"), &sid.tokens["A"]))
+            .await
+            .catch_()?;
+        let res = sesman
+            .inbox(request(msg("second-secret"), &sid.tokens["A"]))
+            .await;
+        assert_throw!(res.is_err());
+
+        let log = String::from_utf8(buf.0.lock().unwrap().clone()).catch_()?;
+        assert_throw!(!log.contains("secret"), "Payloads should not be logged");
+        let mut seen = Vec::new();
+        for line in log.lines() {
+            let event: serde_json::Value = serde_json::from_str(line).catch_()?;
+            let span = &event["span"];
+            if span["op"] != "Inbox" {
+                continue;
+            }
+            assert_throw!(span["session_id"] == sid.value.as_str() && span["player"] == "A");
+            seen.push(
+                event["fields"]["message"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+            );
+        }
+        for message in ["posted", "equivocation"] {
+            assert_throw!(seen.iter().any(|m| m == message), message);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_audits_first_request() -> Resultat<()> {
+        let path = std::env::temp_dir().join(format!("svarog_audit_test_{}", uuid::Uuid::now_v7()));
+        let path = path.to_str().ifnone_()?;
+        let res = async {
+            let store = Arc::new(MemStore::default());
+            let audit = Arc::new(AuditLog::open(path).catch_()?);
+            let (sesman, _) = Sesman::init(settings(), store, audit.clone())
+                .await
+                .catch_()?;
+            let sid = sesman
+                .new_session(Request::new(players(&["A", "B", "C"])))
+                .await
+                .catch_()?
+                .into_inner();
+            let session_id = || SessionId {
+                value: sid.value.clone(),
+                ..Default::default()
+            };
+            let msg = Message {
+                session_id: sid.value.clone(),
+                topic: "topic".to_owned(),
+                src: 1,
+                dst: 2,
+                seq: 0,
+                obj: Some(b"payload".to_vec()),
+            };
+            let msgs = || VecMessage {
+                values: vec![msg.clone()],
+            };
+            // A never joins, and re-sends.
+            for _ in 0..2 {
+                sesman
+                    .inbox(request(msgs(), &sid.tokens["A"]))
+                    .await
+                    .catch_()?;
+            }
+            sesman
+                .outbox(request(msgs(), &sid.tokens["B"]))
+                .await
+                .catch_()?;
+            sesman
+                .join_session(request(session_id(), &sid.tokens["B"]))
+                .await
+                .catch_()?;
+            // The reports do not join.
+            sesman
+                .get_session_status(request(session_id(), &sid.tokens["C"]))
+                .await
+                .catch_()?;
+            sesman
+                .complete_session(request(session_id(), &sid.tokens["C"]))
+                .await
+                .catch_()?;
+            let dup = SessionConfig {
+                session_id: sid.value.clone(),
+                ..players(&["A"])
+            };
+            assert_throw!(sesman.new_session(Request::new(dup)).await.is_err());
+            audit.flush().await
+        }
+        .await;
+        let log = std::fs::read_to_string(path);
+        let _ = std::fs::remove_file(path);
+        res?;
+        let mut events = Vec::new();
+        for line in log.catch_()?.lines() {
+            let (rec, _) = AuditRecord::from_line(line).catch_()?;
+            events.push(format!("{} {}", rec.event, rec.player));
+        }
+        let expected = [
+            "session_created ",
+            "player_joined A",
+            "player_joined B",
+            "player_joined C",
+            "player_completed C",
+            "session_rejected ",
+        ];
+        assert_throw!(events == expected, format!("{:?}", events));
+        Ok(())
+    }
+}
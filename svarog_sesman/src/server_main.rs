@@ -1,63 +1,340 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
 use clap::{value_parser, Arg, ArgAction, Command};
 use erreur::*;
-use svarog_grpc::mpc_session_manager_server::{
-    MpcSessionManagerServer, // server struct
+use svarog_grpc::{
+    mpc_session_manager_server::{
+        MpcSessionManagerServer, // server struct
+    },
+    sesman_admin_server::SesmanAdminServer,
+    Limits,
+};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::oneshot,
+    time::sleep,
 };
-use tonic::transport::{Identity, Server, ServerTlsConfig};
+use tokio_stream::{wrappers::SignalStream, StreamExt};
+use tonic::transport::Server;
+use tracing::{error, info, warn};
 
+mod server_admin;
+use server_admin::Admin;
+mod server_audit;
+use server_audit::AuditLog;
+mod server_cert;
+mod server_config;
+mod server_drain;
+use server_config::Config;
+mod server_health;
 mod server_impl;
+mod server_metrics;
+mod server_rest;
 pub use server_impl::*;
+mod server_session;
+mod server_status;
+mod server_storage;
+use server_storage::{DiskStore, MemStore, Storage};
+mod server_tls;
+use server_tls::TlsReloader;
+mod server_usage;
+
+/// Time for the open requests to end once drained, before exiting anyway.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Limits of resource usage. 0 means unlimited.
+fn limit_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .long(name)
+        .required(false)
+        .value_parser(value_parser!(u64))
+        .help(help)
+        .action(ArgAction::Set)
+}
 
 #[tokio::main]
 async fn main() -> Resultat<()> {
-    // Parse args
+    // Parse args. Those given override the config file and the environment.
     let matches = Command::new("svarog_sesman")
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .required(false)
+                .help("TOML config file. See svarog_sesman.example.toml.")
+                .action(ArgAction::Set),
+        )
         .arg(
             Arg::new("host")
                 .short('h')
                 .required(false)
-                .default_value("0.0.0.0")
                 .action(ArgAction::Set),
         )
         .arg(
             Arg::new("port")
                 .short('p')
                 .required(false)
-                .default_value("2000")
                 .value_parser(value_parser!(u16))
                 .action(ArgAction::Set),
         )
         .arg(Arg::new("https").long("https").action(ArgAction::SetTrue))
+        .arg(
+            Arg::new("mtls")
+                .long("mtls")
+                .help("Require client certificates issued by tls.client_ca. Implies --https.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("default_ttl")
+                .long("default-ttl")
+                .required(false)
+                .value_parser(value_parser!(u64))
+                .help("Lifetime in seconds of sessions created without ttl.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("max_ttl")
+                .long("max-ttl")
+                .required(false)
+                .value_parser(value_parser!(u64))
+                .help("Upper bound in seconds of the ttl of sessions.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("drain_timeout")
+                .long("drain-timeout")
+                .required(false)
+                .value_parser(value_parser!(u64))
+                .help("Seconds to wait for the sessions in progress after SIGTERM.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("db")
+                .long("db")
+                .required(false)
+                .help("Persist sessions to this directory. Keep them in memory if omitted.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("admin_token_file")
+                .long("admin-token-file")
+                .required(false)
+                .help("Serve SesmanAdmin to the bearer of the token in this file. Not served if omitted.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("audit_log")
+                .long("audit-log")
+                .required(false)
+                .help("Append the hash-chained audit log to this file. Not audited if omitted.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("metrics_addr")
+                .long("metrics-addr")
+                .required(false)
+                .help("Serve Prometheus metrics at http://<this address>/metrics. Not served if omitted.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("rest_addr")
+                .long("rest-addr")
+                .required(false)
+                .help("Serve the REST gateway at http(s)://<this address>/v1/. Not served if omitted.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("public_urls")
+                .long("public-urls")
+                .required(false)
+                .help("Comma separated URLs that the players reach sesman at. NewSession rejects other sesman_url if given.")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("log_format")
+                .long("log-format")
+                .required(false)
+                .value_parser(["text", "json"])
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("log_level")
+                .long("log-level")
+                .required(false)
+                .help("Log filter, e.g. debug or info,svarog_sesman=debug. Overridden by RUST_LOG.")
+                .action(ArgAction::Set),
+        )
+        .arg(limit_arg("max-payload", "Bytes of a message."))
+        .arg(limit_arg("max-batch", "Messages in one Inbox or Outbox call."))
+        .arg(limit_arg("max-msgs-per-session", "Messages in a session."))
+        .arg(limit_arg("max-bytes-per-session", "Bytes of messages in a session."))
+        .arg(limit_arg("max-sessions", "Sessions not expired yet."))
+        .arg(limit_arg("max-waiters-per-conn", "Outbox waiters over a connection."))
+        .arg(limit_arg("memory-budget", "Bytes of all the stored messages."))
         .disable_help_flag(true)
         .get_matches();
-    let host: String = matches.get_one::<String>("host").ifnone_()?.to_owned();
-    let port: u16 = matches.get_one::<u16>("port").ifnone_()?.to_owned();
-    let https: bool = matches.get_flag("https");
-    println!("{}", svarog_sesman::version());
-    println!("svarog_sesman will listen on {}:{}", &host, port);
+    let path = matches.get_one::<String>("config").map(|s| s.as_str());
+    let mut cfg = Config::load(path, &std::env::vars().collect()).catch_()?;
+    let str_args = [
+        ("host", &mut cfg.listen.host),
+        ("metrics_addr", &mut cfg.listen.metrics_addr),
+        ("rest_addr", &mut cfg.listen.rest_addr),
+        ("public_urls", &mut cfg.listen.public_urls),
+        ("db", &mut cfg.storage.db),
+        ("admin_token_file", &mut cfg.admin.token_file),
+        ("audit_log", &mut cfg.audit.log),
+        ("log_format", &mut cfg.log.format),
+        ("log_level", &mut cfg.log.level),
+    ];
+    for (id, item) in str_args {
+        if let Some(val) = matches.get_one::<String>(id) {
+            *item = val.clone();
+        }
+    }
+    if let Some(port) = matches.get_one::<u16>("port") {
+        cfg.listen.port = *port;
+    }
+    cfg.tls.https |= matches.get_flag("https");
+    cfg.tls.mtls |= matches.get_flag("mtls");
+    let lim = &mut cfg.limits;
+    let u64_args = [
+        ("default_ttl", &mut cfg.sessions.default_ttl),
+        ("max_ttl", &mut cfg.sessions.max_ttl),
+        ("drain_timeout", &mut cfg.sessions.drain_timeout),
+        ("max-payload", &mut lim.max_payload),
+        ("max-batch", &mut lim.max_batch),
+        ("max-msgs-per-session", &mut lim.max_msgs_per_session),
+        ("max-bytes-per-session", &mut lim.max_bytes_per_session),
+        ("max-sessions", &mut lim.max_sessions),
+        ("max-waiters-per-conn", &mut lim.max_waiters_per_conn),
+        ("memory-budget", &mut lim.memory_budget),
+    ];
+    for (id, item) in u64_args {
+        if let Some(val) = matches.get_one::<u64>(id) {
+            *item = *val;
+        }
+    }
+    cfg.validate().catch_()?;
+
+    svarog_sesman::init_tracing(cfg.log.format == "json", &cfg.log.level).catch_()?;
+    info!("{}", svarog_sesman::version());
+    info!("Effective configuration:\n{}", cfg.to_toml().catch_()?);
+    let addr = cfg.listen_addr().catch_()?;
+    info!("svarog_sesman will listen on {}", addr);
 
     // Init service
-    let (sesman, recycle_task_handle) = Sesman::init().await.catch_()?;
-
-    // Start server
-    let mut server = Server::builder();
-    if https {
-        let cert = tokio::fs::read_to_string("tls/cert.pem").await.catch_()?;
-        let key = tokio::fs::read_to_string("tls/privkey.pem")
-            .await
-            .catch_()?;
-        let ident = Identity::from_pem(cert, key);
-        server = server
-            .tls_config(ServerTlsConfig::new().identity(ident))
-            .catch_()?;
+    let settings = Settings {
+        default_ttl: cfg.sessions.default_ttl,
+        max_ttl: cfg.sessions.max_ttl,
+        recycle_interval: cfg.sessions.recycle_interval,
+        mtls: cfg.tls.mtls,
+        public_urls: cfg.public_urls(),
+        limits: Limits::from(&cfg.limits),
+    };
+    let store: Arc<dyn Storage> = match cfg.storage.db.as_str() {
+        "" => Arc::new(MemStore::default()),
+        path => {
+            info!("svarog_sesman will persist sessions to {}", path);
+            Arc::new(DiskStore::open(path).catch_()?)
+        }
+    };
+    let audit = Arc::new(match cfg.audit.log.as_str() {
+        "" => AuditLog::default(),
+        path => AuditLog::open(path).catch_()?,
+    });
+    let (sesman, recycle_task_handle) = Sesman::init(settings, store, audit.clone())
+        .await
+        .catch_()?;
+    if !cfg.listen.metrics_addr.is_empty() {
+        let addr = cfg.metrics_addr().catch_()?;
+        info!(
+            "svarog_sesman will serve metrics on http://{}/metrics",
+            addr
+        );
+        let sesman = sesman.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server_metrics::serve(sesman, addr).await {
+                error!("{}", e);
+            }
+        });
     }
-    server
+    // Our own TLS listeners, so that certificates can be reloaded.
+    let tls = if cfg.https() {
+        let tls = TlsReloader::new(cfg.tls.clone()).catch_()?;
+        tls.watch().catch_()?;
+        Some(tls)
+    } else {
+        None
+    };
+    if !cfg.listen.rest_addr.is_empty() {
+        let addr = cfg.rest_addr().catch_()?;
+        let scheme = if cfg.https() { "https" } else { "http" };
+        info!(
+            "svarog_sesman will serve the REST gateway on {}://{}/v1/",
+            scheme, addr
+        );
+        let sesman = sesman.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server_rest::serve(sesman, addr, tls).await {
+                error!("{}", e);
+            }
+        });
+    }
+    let admin = match cfg.admin.token_file.as_str() {
+        "" => None,
+        path => {
+            let token = tokio::fs::read_to_string(path)
+                .await
+                .catch("", format!("Try reading admin token from {}", path))?;
+            let admin = Admin::new(sesman.clone(), token.trim()).catch_()?;
+            Some(SesmanAdminServer::new(admin))
+        }
+    };
+
+    let (health, health_service) = server_health::health_service(admin.is_some()).await;
+    let reflection_service = server_health::reflection_service().catch_()?;
+
+    // Start server, until drained.
+    let stop = SignalStream::new(signal(SignalKind::terminate()).catch_()?)
+        .merge(SignalStream::new(signal(SignalKind::interrupt()).catch_()?));
+    let (drained_tx, drained_rx) = oneshot::channel::<()>();
+    let shutdown = {
+        let drain = server_drain::drain(
+            sesman.clone(),
+            health.clone(),
+            stop,
+            Duration::from_secs(cfg.sessions.drain_timeout),
+        );
+        async move {
+            drain.await;
+            let _ = drained_tx.send(());
+        }
+    };
+    let router = Server::builder()
+        .add_service(health_service)
+        .add_service(reflection_service)
         .add_service(MpcSessionManagerServer::new(sesman))
-        .serve(format!("{host}:{port}").parse().unwrap())
-        .await
-        .catch("GrpcServerIsDown", "MpcSessionManager")?;
+        .add_optional_service(admin);
+    let serve: Pin<Box<dyn Future<Output = Result<(), tonic::transport::Error>>>> = match tls {
+        Some(tls) => {
+            let incoming = tls.incoming(addr).await.catch_()?;
+            Box::pin(router.serve_with_incoming_shutdown(incoming, shutdown))
+        }
+        None => Box::pin(router.serve_with_shutdown(addr, shutdown)),
+    };
+    tokio::select! {
+        res = serve => res.catch("GrpcServerIsDown", "MpcSessionManager")?,
+        _ = async {
+            let _ = drained_rx.await;
+            sleep(SHUTDOWN_GRACE).await;
+        } => warn!("Requests still open {:?} after draining, exit anyway", SHUTDOWN_GRACE),
+    }
 
     recycle_task_handle.abort();
+    if let Err(e) = audit.flush().await {
+        error!("Failed to flush audit log: {}", e);
+    }
 
     Ok(())
 }
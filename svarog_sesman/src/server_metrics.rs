@@ -0,0 +1,220 @@
+//! Prometheus metrics of sesman, served over plain HTTP at `/metrics`.
+//!
+//! Counters and histograms are updated as the requests are served.
+//! Gauges are sampled when scraped.
+
+use std::{convert::Infallible, net::SocketAddr};
+
+use erreur::*;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Response, Server, StatusCode,
+};
+use prometheus::{
+    core::Collector, exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use svarog_grpc::Message;
+
+use crate::Sesman;
+
+pub struct Metrics {
+    registry: Registry,
+    pub sessions_created: IntCounter,
+    pub sessions_active: IntGauge,
+    pub sessions_expired: IntCounter,
+    /// Labeled by rpc and direction, `in` for posted and `out` for delivered.
+    pub messages: IntCounterVec,
+    /// Labeled the same way as `messages`.
+    pub payload_bytes: HistogramVec,
+    /// Time from a request for messages until they all arrive, labeled by rpc.
+    pub wait_seconds: HistogramVec,
+    pub waiters: IntGauge,
+    pub store_entries: IntGauge,
+    pub recycled_entries: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Resultat<Self> {
+        let registry = Registry::new_custom(Some("svarog_sesman".to_owned()), None).catch_()?;
+        let sessions_created =
+            IntCounter::new("sessions_created_total", "Sessions created.").catch_()?;
+        let sessions_active =
+            IntGauge::new("sessions_active", "Sessions not expired yet.").catch_()?;
+        let sessions_expired =
+            IntCounter::new("sessions_expired_total", "Sessions removed after expiry.").catch_()?;
+        let messages = IntCounterVec::new(
+            Opts::new("messages_total", "Messages posted or delivered."),
+            &["rpc", "direction"],
+        )
+        .catch_()?;
+        let payload_bytes = HistogramVec::new(
+            HistogramOpts::new("payload_bytes", "Payload sizes of the messages.")
+                .buckets(exponential_buckets(64.0, 4.0, 10).catch_()?),
+            &["rpc", "direction"],
+        )
+        .catch_()?;
+        let wait_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "wait_seconds",
+                "Time waited for the requested messages to arrive.",
+            )
+            .buckets(exponential_buckets(0.001, 4.0, 10).catch_()?),
+            &["rpc"],
+        )
+        .catch_()?;
+        let waiters =
+            IntGauge::new("waiters", "Requests blocked waiting for messages.").catch_()?;
+        let store_entries =
+            IntGauge::new("store_entries", "Messages kept in the storage.").catch_()?;
+        let recycled_entries = IntCounter::new(
+            "recycled_entries_total",
+            "Messages removed by the recycle task.",
+        )
+        .catch_()?;
+
+        let collectors: [Box<dyn Collector>; 9] = [
+            Box::new(sessions_created.clone()),
+            Box::new(sessions_active.clone()),
+            Box::new(sessions_expired.clone()),
+            Box::new(messages.clone()),
+            Box::new(payload_bytes.clone()),
+            Box::new(wait_seconds.clone()),
+            Box::new(waiters.clone()),
+            Box::new(store_entries.clone()),
+            Box::new(recycled_entries.clone()),
+        ];
+        for collector in collectors {
+            registry.register(collector).catch_()?;
+        }
+
+        Ok(Self {
+            registry,
+            sessions_created,
+            sessions_active,
+            sessions_expired,
+            messages,
+            payload_bytes,
+            wait_seconds,
+            waiters,
+            store_entries,
+            recycled_entries,
+        })
+    }
+
+    /// Count the messages and observe their payload sizes.
+    pub fn observe_msgs(&self, rpc: &str, direction: &str, msgs: &[Message]) {
+        let labels = [rpc, direction];
+        let counter = self.messages.with_label_values(&labels);
+        let histogram = self.payload_bytes.with_label_values(&labels);
+        for msg in msgs.iter() {
+            counter.inc();
+            histogram.observe(msg.obj.as_ref().map_or(0, |obj| obj.len()) as f64);
+        }
+    }
+
+    fn encode(&self) -> Resultat<Vec<u8>> {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .catch_()?;
+        Ok(buf)
+    }
+}
+
+/// Serve `/metrics` until the process exits.
+pub async fn serve(sesman: Sesman, addr: SocketAddr) -> Resultat<()> {
+    let make_svc = make_service_fn(move |_| {
+        let sesman = sesman.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let sesman = sesman.clone();
+                async move {
+                    if req.uri().path() != "/metrics" {
+                        let mut resp = Response::new(Body::from("Not found"));
+                        *resp.status_mut() = StatusCode::NOT_FOUND;
+                        return Ok::<_, Infallible>(resp);
+                    }
+                    sesman.sample_metrics();
+                    let resp = match sesman.metrics().encode() {
+                        Ok(buf) => {
+                            let mut resp = Response::new(Body::from(buf));
+                            resp.headers_mut().insert(
+                                CONTENT_TYPE,
+                                TextEncoder::new().format_type().parse().unwrap(),
+                            );
+                            resp
+                        }
+                        Err(e) => {
+                            let mut resp = Response::new(Body::from(e.to_string()));
+                            *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                            resp
+                        }
+                    };
+                    Ok::<_, Infallible>(resp)
+                }
+            }))
+        }
+    });
+    Server::try_bind(&addr)
+        .catch("", format!("Try binding metrics endpoint to {}", addr))?
+        .serve(make_svc)
+        .await
+        .catch("MetricsServerIsDown", "")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use svarog_grpc::{mpc_session_manager_server::MpcSessionManager, Message, VecMessage};
+    use tonic::Request;
+
+    use crate::server_impl::tests::{players, request, sesman, settings};
+
+    #[tokio::test]
+    async fn test_metrics() -> Resultat<()> {
+        let sesman = sesman(settings()).await?;
+        let sid = sesman
+            .new_session(Request::new(players(&["A", "B"])))
+            .await
+            .catch_()?
+            .into_inner();
+        let msgs = VecMessage {
+            values: vec![Message {
+                session_id: sid.value.clone(),
+                topic: "t".to_owned(),
+                src: 1,
+                dst: 0,
+                seq: 0,
+                obj: Some(vec![0; 100]),
+            }],
+        };
+        sesman
+            .inbox(request(msgs.clone(), &sid.tokens["A"]))
+            .await
+            .catch_()?;
+        sesman
+            .outbox(request(msgs, &sid.tokens["B"]))
+            .await
+            .catch_()?;
+
+        sesman.sample_metrics();
+        let text = String::from_utf8(sesman.metrics().encode()?).catch_()?;
+        for line in [
+            "svarog_sesman_sessions_created_total 1",
+            "svarog_sesman_sessions_active 1",
+            "svarog_sesman_store_entries 1",
+            "svarog_sesman_waiters 0",
+            r#"svarog_sesman_messages_total{direction="in",rpc="Inbox"} 1"#,
+            r#"svarog_sesman_messages_total{direction="out",rpc="Outbox"} 1"#,
+            r#"svarog_sesman_payload_bytes_bucket{direction="in",rpc="Inbox",le="64"} 0"#,
+            r#"svarog_sesman_payload_bytes_bucket{direction="in",rpc="Inbox",le="256"} 1"#,
+            r#"svarog_sesman_wait_seconds_count{rpc="Outbox"} 1"#,
+        ] {
+            assert_throw!(text.lines().any(|l| l == line), line);
+        }
+        Ok(())
+    }
+}
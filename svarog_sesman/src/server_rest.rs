@@ -0,0 +1,543 @@
+//! REST gateway of `MpcSessionManager`, for clients where gRPC over HTTP/2 is awkward,
+//! e.g. browsers and mobile apps.
+//!
+//! Requests and responses are the JSON of the `svarog_grpc` types, with `Message.obj`
+//! in base64. The token goes in the `authorization` header, the same as over gRPC.
+//!
+//! | Endpoint                                  | RPC                |
+//! |-------------------------------------------|--------------------|
+//! | `GET  /v1/ping`                           | `Ping`             |
+//! | `POST /v1/sessions`                       | `NewSession`       |
+//! | `GET  /v1/sessions/<id>/config`           | `GetSessionConfig` |
+//! | `POST /v1/sessions/<id>/inbox`            | `Inbox`            |
+//! | `POST /v1/sessions/<id>/outbox?wait=<s>`  | `Outbox`           |
+//! | `POST /v1/sessions/<id>/join`             | `JoinSession`      |
+//! | `POST /v1/sessions/<id>/complete`         | `CompleteSession`  |
+//! | `POST /v1/sessions/<id>/abort`            | `AbortSession`     |
+//!
+//! `Outbox` is a long poll. If the messages do not all arrive within `wait` seconds,
+//! it answers 204 No Content, and the client polls again.
+//! `AbortSession` takes a body of `{"reason": <reason>}`.
+//! Under mTLS, the client certificate binds players the same as over gRPC.
+//! Errors are answered with the HTTP status closest to the gRPC code,
+//! and a body of `{"code": <gRPC code>, "message": <message>}`.
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use erreur::*;
+use hyper::{
+    body::HttpBody,
+    header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        AUTHORIZATION, CONTENT_TYPE,
+    },
+    http::request::Parts,
+    server::{accept, conn::AddrStream},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request as HttpRequest, Response, Server, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use svarog_grpc::{
+    mpc_session_manager_server::MpcSessionManager, AbortRequest, SessionConfig, SessionId,
+    VecMessage, Void,
+};
+use tokio::net::TcpStream;
+use tokio_rustls::server::TlsStream;
+use tonic::{Code, Request, Status};
+
+use crate::{server_tls::TlsReloader, Sesman};
+
+/// How long `Outbox` waits if the client does not say.
+const DEFAULT_WAIT: Duration = Duration::from_secs(30);
+/// At most this long, so that proxies in between do not cut the request.
+const MAX_WAIT: Duration = Duration::from_secs(120);
+/// Same as the default of gRPC, with room for base64.
+const MAX_BODY: usize = 8 << 20;
+
+/// Remote address of a REST client, which stands in for that of a gRPC connection.
+#[derive(Clone, Copy)]
+pub struct RestPeer(pub SocketAddr);
+
+/// Leaf certificate, in DER, that a REST client presented over mTLS,
+/// which stands in for the peer certificates of a gRPC connection.
+#[derive(Clone)]
+pub struct RestPeerCert(pub Vec<u8>);
+
+/// What the gateway knows about the connection of a REST client.
+#[derive(Clone, Default)]
+struct Conn {
+    peer: Option<SocketAddr>,
+    cert: Option<RestPeerCert>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: i32,
+    message: &'a str,
+}
+
+/// As mapped by grpc-gateway.
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::OutOfRange | Code::FailedPrecondition => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Cancelled => StatusCode::REQUEST_TIMEOUT,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Browsers may call from any origin, since the token is not a cookie.
+fn with_cors(mut resp: Response<Body>) -> Response<Body> {
+    let headers = resp.headers_mut();
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, "*".parse().unwrap());
+    headers.insert(
+        ACCESS_CONTROL_ALLOW_HEADERS,
+        "authorization, content-type".parse().unwrap(),
+    );
+    headers.insert(
+        ACCESS_CONTROL_ALLOW_METHODS,
+        "GET, POST, OPTIONS".parse().unwrap(),
+    );
+    resp
+}
+
+fn json_response<T: Serialize>(status: StatusCode, val: &T) -> Response<Body> {
+    let body = serde_json::to_vec(val).unwrap_or_default();
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    resp
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    let mut resp = Response::new(Body::empty());
+    *resp.status_mut() = status;
+    resp
+}
+
+fn error_response(status: Status) -> Response<Body> {
+    let body = ErrorBody {
+        code: status.code() as i32,
+        message: status.message(),
+    };
+    json_response(http_status(status.code()), &body)
+}
+
+async fn read_json<T: DeserializeOwned>(mut body: Body) -> Result<T, Status> {
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| Status::cancelled(e.to_string()))?;
+        if buf.len() + chunk.len() > MAX_BODY {
+            return Err(Status::resource_exhausted(format!(
+                "Request body exceeds {} bytes",
+                MAX_BODY
+            )));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&buf).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// The gRPC request that the REST request stands for.
+#[allow(clippy::result_large_err)]
+fn grpc_request<T>(parts: &Parts, conn: &Conn, msg: T) -> Result<Request<T>, Status> {
+    let mut req = Request::new(msg);
+    if let Some(auth) = parts.headers.get(AUTHORIZATION) {
+        let auth = auth
+            .to_str()
+            .ok()
+            .and_then(|auth| auth.parse().ok())
+            .ok_or_else(|| Status::unauthenticated("Malformed authorization header"))?;
+        req.metadata_mut().insert("authorization", auth);
+    }
+    if let Some(peer) = conn.peer {
+        req.extensions_mut().insert(RestPeer(peer));
+    }
+    if let Some(cert) = conn.cert.clone() {
+        req.extensions_mut().insert(cert);
+    }
+    Ok(req)
+}
+
+/// Messages may leave `session_id` empty, for the one in the path.
+#[allow(clippy::result_large_err)]
+fn in_session(mut msgs: VecMessage, sid: &str) -> Result<VecMessage, Status> {
+    for msg in msgs.values.iter_mut() {
+        if msg.session_id.is_empty() {
+            msg.session_id = sid.to_owned();
+        } else if msg.session_id != sid {
+            return Err(Status::invalid_argument(format!(
+                "Message of session {} posted to session {}",
+                &msg.session_id, sid
+            )));
+        }
+    }
+    Ok(msgs)
+}
+
+fn session_id(sid: &str) -> SessionId {
+    SessionId {
+        value: sid.to_owned(),
+        ..Default::default()
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn wait_of(parts: &Parts) -> Result<Duration, Status> {
+    let wait = parts
+        .uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("wait="));
+    let Some(wait) = wait else {
+        return Ok(DEFAULT_WAIT);
+    };
+    let secs: u64 = wait
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("Invalid wait {:?}", wait)))?;
+    Ok(Duration::from_secs(secs).min(MAX_WAIT))
+}
+
+async fn handle(
+    sesman: Sesman,
+    conn: Conn,
+    req: HttpRequest<Body>,
+) -> Result<Response<Body>, Status> {
+    let (parts, body) = req.into_parts();
+    if parts.method == Method::OPTIONS {
+        return Ok(empty_response(StatusCode::NO_CONTENT));
+    }
+    let path: Vec<&str> = parts.uri.path().trim_matches('/').split('/').collect();
+    let resp = match (&parts.method, path.as_slice()) {
+        (&Method::GET, ["v1", "ping"]) => {
+            let req = grpc_request(&parts, &conn, Void {})?;
+            let resp = sesman.ping(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        (&Method::POST, ["v1", "sessions"]) => {
+            let cfg: SessionConfig = read_json(body).await?;
+            let req = grpc_request(&parts, &conn, cfg)?;
+            let resp = sesman.new_session(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        (&Method::GET, ["v1", "sessions", sid, "config"]) => {
+            let req = grpc_request(&parts, &conn, session_id(sid))?;
+            let resp = sesman.get_session_config(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        (&Method::POST, ["v1", "sessions", sid, "inbox"]) => {
+            let msgs = in_session(read_json(body).await?, sid)?;
+            let req = grpc_request(&parts, &conn, msgs)?;
+            let resp = sesman.inbox(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        (&Method::POST, ["v1", "sessions", sid, "outbox"]) => {
+            let wait = wait_of(&parts)?;
+            let idxs = in_session(read_json(body).await?, sid)?;
+            let req = grpc_request(&parts, &conn, idxs)?;
+            match tokio::time::timeout(wait, sesman.outbox(req)).await {
+                Ok(resp) => json_response(StatusCode::OK, resp?.get_ref()),
+                Err(_) => empty_response(StatusCode::NO_CONTENT),
+            }
+        }
+        (&Method::POST, ["v1", "sessions", sid, "join"]) => {
+            let req = grpc_request(&parts, &conn, session_id(sid))?;
+            let resp = sesman.join_session(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        (&Method::POST, ["v1", "sessions", sid, "complete"]) => {
+            let req = grpc_request(&parts, &conn, session_id(sid))?;
+            let resp = sesman.complete_session(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        (&Method::POST, ["v1", "sessions", sid, "abort"]) => {
+            let mut abort: AbortRequest = read_json(body).await?;
+            if abort.session_id.is_empty() {
+                abort.session_id = sid.to_string();
+            } else if abort.session_id != *sid {
+                return Err(Status::invalid_argument(format!(
+                    "Abort of session {} posted to session {}",
+                    &abort.session_id, sid
+                )));
+            }
+            let req = grpc_request(&parts, &conn, abort)?;
+            let resp = sesman.abort_session(req).await?;
+            json_response(StatusCode::OK, resp.get_ref())
+        }
+        _ => {
+            return Err(Status::not_found(format!(
+                "No endpoint {} {}",
+                &parts.method,
+                parts.uri.path()
+            )));
+        }
+    };
+    Ok(resp)
+}
+
+async fn respond(
+    sesman: Sesman,
+    conn: Conn,
+    req: HttpRequest<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let resp = handle(sesman, conn, req)
+        .await
+        .unwrap_or_else(error_response);
+    Ok(with_cors(resp))
+}
+
+/// Serve the gateway until the process exits, over the TLS of the gRPC listener if any.
+pub async fn serve(
+    sesman: Sesman,
+    addr: SocketAddr,
+    tls: Option<Arc<TlsReloader>>,
+) -> Resultat<()> {
+    match tls {
+        Some(tls) => {
+            let make_svc = make_service_fn(move |stream: &TlsStream<TcpStream>| {
+                let (tcp, tls) = stream.get_ref();
+                let conn = Conn {
+                    peer: tcp.peer_addr().ok(),
+                    cert: tls
+                        .peer_certificates()
+                        .and_then(|certs| certs.first())
+                        .map(|leaf| RestPeerCert(leaf.to_vec())),
+                };
+                let sesman = sesman.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        respond(sesman.clone(), conn.clone(), req)
+                    }))
+                }
+            });
+            let incoming = tls.incoming(addr).await.catch_()?;
+            Server::builder(accept::from_stream(incoming))
+                .serve(make_svc)
+                .await
+                .catch("RestServerIsDown", "")?;
+        }
+        None => {
+            let make_svc = make_service_fn(move |stream: &AddrStream| {
+                let conn = Conn {
+                    peer: Some(stream.remote_addr()),
+                    cert: None,
+                };
+                let sesman = sesman.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        respond(sesman.clone(), conn.clone(), req)
+                    }))
+                }
+            });
+            Server::try_bind(&addr)
+                .catch("", format!("Try binding REST gateway to {}", addr))?
+                .serve(make_svc)
+                .await
+                .catch("RestServerIsDown", "")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use erreur::*;
+    use hyper::{header::AUTHORIZATION, Body, Method, Request, StatusCode};
+    use serde::{de::DeserializeOwned, Serialize};
+    use svarog_grpc::{
+        mpc_session_manager_server::MpcSessionManager, AbortRequest, CertBinding, JoinReply,
+        Message, SessionConfig, SessionId, SessionState, VecMessage,
+    };
+
+    use super::{respond, session_id, Conn, RestPeerCert};
+    use crate::{
+        server_impl::tests::{players, request, sesman, settings},
+        Sesman,
+    };
+
+    /// Answer the request the way the gateway would. Return the status and the body.
+    async fn call<T: Serialize>(
+        sesman: &Sesman,
+        method: Method,
+        path: &str,
+        token: &str,
+        body: Option<&T>,
+    ) -> Resultat<(StatusCode, Vec<u8>)> {
+        call_over(sesman, Conn::default(), method, path, token, body).await
+    }
+
+    /// Like `call`, over `conn`.
+    async fn call_over<T: Serialize>(
+        sesman: &Sesman,
+        conn: Conn,
+        method: Method,
+        path: &str,
+        token: &str,
+        body: Option<&T>,
+    ) -> Resultat<(StatusCode, Vec<u8>)> {
+        let body = match body {
+            Some(body) => Body::from(serde_json::to_vec(body).catch_()?),
+            None => Body::empty(),
+        };
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .body(body)
+            .catch_()?;
+        let resp = respond(sesman.clone(), conn, req).await.catch_()?;
+        let status = resp.status();
+        let body = hyper::body::to_bytes(resp.into_body()).await.catch_()?;
+        Ok((status, body.to_vec()))
+    }
+
+    fn parse<T: DeserializeOwned>(body: &[u8]) -> Resultat<T> {
+        serde_json::from_slice(body).catch_()
+    }
+
+    #[tokio::test]
+    async fn test_routes() -> Resultat<()> {
+        let sesman = sesman(settings()).await.catch_()?;
+        let none = None::<&()>;
+        let (status, _) = call(&sesman, Method::GET, "/v1/ping", "", none).await?;
+        assert_throw!(status == StatusCode::OK);
+
+        let cfg = players(&["A", "B"]);
+        let (status, body) = call(&sesman, Method::POST, "/v1/sessions", "", Some(&cfg)).await?;
+        assert_throw!(status == StatusCode::OK);
+        let sid: SessionId = parse(&body)?;
+        let path = |route: &str| format!("/v1/sessions/{}/{}", &sid.value, route);
+        let (ta, tb) = (&sid.tokens["A"], &sid.tokens["B"]);
+
+        let (status, body) = call(&sesman, Method::GET, &path("config"), ta, none).await?;
+        assert_throw!(status == StatusCode::OK);
+        assert_throw!(parse::<SessionConfig>(&body)?.players.len() == 2);
+
+        let (status, body) = call(&sesman, Method::POST, &path("join"), ta, none).await?;
+        assert_throw!(status == StatusCode::OK);
+        assert_throw!(parse::<JoinReply>(&body)?.player == "A");
+
+        let msg = Message {
+            topic: "topic".to_owned(),
+            src: 1,
+            dst: 2,
+            obj: Some(b"payload".to_vec()),
+            ..Default::default()
+        };
+        let msgs = VecMessage {
+            values: vec![msg.clone()],
+        };
+        let (status, _) = call(&sesman, Method::POST, &path("inbox"), ta, Some(&msgs)).await?;
+        assert_throw!(status == StatusCode::OK);
+        let idxs = VecMessage {
+            values: vec![Message { obj: None, ..msg }],
+        };
+        let outbox = path("outbox?wait=1");
+        let (status, body) = call(&sesman, Method::POST, &outbox, tb, Some(&idxs)).await?;
+        assert_throw!(status == StatusCode::OK);
+        let got: VecMessage = parse(&body)?;
+        assert_throw!(got.values[0].obj.as_deref() == Some(&b"payload"[..]));
+
+        for token in [ta, tb] {
+            let (status, _) = call(&sesman, Method::POST, &path("complete"), token, none).await?;
+            assert_throw!(status == StatusCode::OK);
+        }
+        let req = request(session_id(&sid.value), ta);
+        let status = sesman.get_session_status(req).await.catch_()?.into_inner();
+        assert_throw!(status.state == SessionState::Completed as i32);
+
+        let (status, _) = call(&sesman, Method::GET, "/v1/nowhere", "", none).await?;
+        assert_throw!(status == StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_abort_route() -> Resultat<()> {
+        let sesman = sesman(settings()).await.catch_()?;
+        let none = None::<&()>;
+        let cfg = players(&["A", "B"]);
+        let (_, body) = call(&sesman, Method::POST, "/v1/sessions", "", Some(&cfg)).await?;
+        let sid: SessionId = parse(&body)?;
+        let path = |route: &str| format!("/v1/sessions/{}/{}", &sid.value, route);
+        let (ta, tb) = (&sid.tokens["A"], &sid.tokens["B"]);
+
+        let other = AbortRequest {
+            session_id: "other".to_owned(),
+            reason: "bye".to_owned(),
+        };
+        let (status, _) = call(&sesman, Method::POST, &path("abort"), ta, Some(&other)).await?;
+        assert_throw!(status == StatusCode::BAD_REQUEST);
+        let (status, _) = call(&sesman, Method::POST, &path("abort"), "bad", none).await?;
+        assert_throw!(status != StatusCode::OK);
+
+        let abort = AbortRequest {
+            reason: "bye".to_owned(),
+            ..Default::default()
+        };
+        let (status, _) = call(&sesman, Method::POST, &path("abort"), ta, Some(&abort)).await?;
+        assert_throw!(status == StatusCode::OK);
+        // The others fail at once.
+        let idxs = VecMessage {
+            values: vec![Message {
+                topic: "topic".to_owned(),
+                src: 1,
+                dst: 2,
+                ..Default::default()
+            }],
+        };
+        let outbox = path("outbox?wait=5");
+        let (status, body) = call(&sesman, Method::POST, &outbox, tb, Some(&idxs)).await?;
+        assert_throw!(status == StatusCode::CONFLICT);
+        assert_throw!(String::from_utf8_lossy(&body).contains("bye"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cert_binding() -> Resultat<()> {
+        let mut settings = settings();
+        settings.mtls = true;
+        let sesman = sesman(settings).await.catch_()?;
+        let binding = CertBinding {
+            subject: "C=CN, O=Taiyi, CN=Alice".to_owned(),
+            ..Default::default()
+        };
+        let cfg = SessionConfig {
+            player_certs: [("A".to_owned(), binding)].into(),
+            ..players(&["A", "B"])
+        };
+        let none = None::<&()>;
+        let (_, body) = call(&sesman, Method::POST, "/v1/sessions", "", Some(&cfg)).await?;
+        let sid: SessionId = parse(&body)?;
+        let join = format!("/v1/sessions/{}/join", &sid.value);
+        let ta = &sid.tokens["A"];
+
+        let (status, _) = call(&sesman, Method::POST, &join, ta, none).await?;
+        assert_throw!(status == StatusCode::UNAUTHORIZED);
+        let pem =
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/alice.pem")).catch_()?;
+        let der = rustls_pemfile::certs(&mut pem.as_slice())
+            .next()
+            .ifnone_()?
+            .catch_()?;
+        let alice = Conn {
+            peer: None,
+            cert: Some(RestPeerCert(der.to_vec())),
+        };
+        let (status, body) = call_over(&sesman, alice, Method::POST, &join, ta, none).await?;
+        assert_throw!(status == StatusCode::OK);
+        assert_throw!(parse::<JoinReply>(&body)?.player == "A");
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Session records of sesman.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use blake2::digest::{Update, VariableOutput};
+use erreur::*;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use svarog_grpc::{Equivocation, SessionConfig, SessionState};
+use svarog_sesman::{name_indices, player_indices};
+
+use crate::session_handle;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub cfg: SessionConfig,
+
+    /// Prefix of the primary keys of the messages in this session.
+    pub handle: [u8; 16],
+
+    /// Unix time in milliseconds.
+    pub created_at: u64,
+
+    /// Unix time in milliseconds.
+    pub expire_at: u64,
+
+    /// Reason of the abortion, if aborted.
+    #[serde(default)]
+    pub aborted: Option<String>,
+
+    /// Keyed by the digest of the token.
+    #[serde(default)]
+    pub grants: HashMap<String, Grant>,
+
+    /// Slots written twice with different payloads, at most once per slot.
+    #[serde(default)]
+    pub equivocations: Vec<Equivocation>,
+
+    /// Never `Expired`, which is told by the clock instead. See `state_at`.
+    #[serde(default)]
+    pub state: SessionState,
+
+    /// Unix times in milliseconds, keyed by player name.
+    #[serde(default)]
+    pub joined: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub completed: BTreeMap<String, u64>,
+
+    /// Unix time in milliseconds when the session completed or was aborted.
+    #[serde(default)]
+    pub finished_at: u64,
+}
+
+/// What the holder of a token may do.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Grant {
+    pub name: String,
+
+    /// The indices that the holder may send messages as,
+    /// and receive point-to-point messages as.
+    pub indices: BTreeSet<u64>,
+}
+
+impl SessionRecord {
+    /// `cfg.ttl` should have been validated.
+    pub fn new(cfg: SessionConfig) -> Resultat<Self> {
+        let created_at = now_ms();
+        let expire_at = created_at + cfg.ttl * 1000;
+        let handle = session_handle(&cfg.session_id, expire_at).catch_()?;
+        Ok(Self {
+            cfg,
+            handle,
+            created_at,
+            expire_at,
+            aborted: None,
+            grants: HashMap::new(),
+            equivocations: Vec::new(),
+            state: SessionState::Created,
+            joined: BTreeMap::new(),
+            completed: BTreeMap::new(),
+            finished_at: 0,
+        })
+    }
+
+    pub fn state_at(&self, now: u64) -> SessionState {
+        match self.state {
+            SessionState::Completed | SessionState::Aborted => self.state,
+            _ if self.aborted.is_some() => SessionState::Aborted,
+            _ if self.is_expired(now) => SessionState::Expired,
+            state => state,
+        }
+    }
+
+    /// The attending players, either in `players` or in `players_reshared`.
+    /// The session completes once every one of them completes.
+    pub fn participants(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        for players in [&self.cfg.players, &self.cfg.players_reshared] {
+            names.extend(player_indices(players).into_keys());
+        }
+        names
+    }
+
+    pub fn join(&mut self, name: &str, now: u64) {
+        self.joined.entry(name.to_owned()).or_insert(now);
+        if self.state == SessionState::Created {
+            self.state = SessionState::Running;
+        }
+    }
+
+    pub fn complete(&mut self, name: &str, now: u64) {
+        self.join(name, now);
+        self.completed.entry(name.to_owned()).or_insert(now);
+        let participants = self.participants();
+        if participants.iter().all(|p| self.completed.contains_key(p)) {
+            self.state = SessionState::Completed;
+            self.finished_at = now;
+        }
+    }
+
+    pub fn abort(&mut self, reason: &str, now: u64) {
+        self.aborted = Some(reason.to_owned());
+        self.state = SessionState::Aborted;
+        self.finished_at = now;
+    }
+
+    /// Issue a token for each party in `name_indices`.
+    /// Return the tokens by player name.
+    pub fn issue_tokens(&mut self) -> Resultat<HashMap<String, String>> {
+        let mut tokens = HashMap::new();
+        self.grants.clear();
+        for (name, indices) in self.name_indices() {
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            let token = hex::encode(bytes);
+            let grant = Grant {
+                name: name.clone(),
+                indices,
+            };
+            self.grants.insert(token_digest(&token).catch_()?, grant);
+            tokens.insert(name, token);
+        }
+        Ok(tokens)
+    }
+
+    /// See `svarog_sesman::name_indices`.
+    pub fn name_indices(&self) -> BTreeMap<String, BTreeSet<u64>> {
+        name_indices(&self.cfg)
+    }
+
+    pub fn grant(&self, token: &str) -> Option<&Grant> {
+        let digest = token_digest(token).ok()?;
+        self.grants.get(&digest)
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expire_at <= now
+    }
+
+    /// Remaining lifetime in milliseconds.
+    pub fn remaining_ms(&self, now: u64) -> u64 {
+        self.expire_at.saturating_sub(now)
+    }
+}
+
+/// Only digests of the tokens are stored.
+pub fn token_digest(token: &str) -> Resultat<String> {
+    let mut digest = [0u8; 32];
+    let mut ha = blake2::Blake2bVar::new(32).catch_()?;
+    ha.update(token.as_bytes());
+    ha.finalize_variable(&mut digest).catch_()?;
+    Ok(hex::encode(digest))
+}
+
+pub fn now_ms() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
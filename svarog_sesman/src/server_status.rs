@@ -0,0 +1,69 @@
+//! Who is waiting for which messages, for `GetSessionStatus`.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use svarog_grpc::WaitingMessage;
+
+/// Keyed by the session handle and the id of the waiter,
+/// so that the waiters of a session are adjacent.
+type Entries = BTreeMap<([u8; 16], u64), Vec<([u8; 32], WaitingMessage)>>;
+
+#[derive(Default)]
+pub struct Waiters {
+    next_id: AtomicU64,
+    entries: Mutex<Entries>,
+}
+
+impl Waiters {
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register the messages that a waiter is waiting for, by their primary keys.
+    /// They are registered until the guard is dropped.
+    pub fn register(self: &Arc<Self>, msgs: Vec<([u8; 32], WaitingMessage)>) -> WaiterEntry {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries();
+        let mut keys = Vec::new();
+        for (key, msg) in msgs {
+            let mut handle = [0u8; 16];
+            handle.copy_from_slice(&key[..16]);
+            entries.entry((handle, id)).or_default().push((key, msg));
+            keys.push((handle, id));
+        }
+        keys.sort();
+        keys.dedup();
+        WaiterEntry {
+            waiters: self.clone(),
+            keys,
+        }
+    }
+
+    /// The messages being waited for in the session, by their primary keys.
+    pub fn of_session(&self, handle: &[u8; 16]) -> Vec<([u8; 32], WaitingMessage)> {
+        self.entries()
+            .range((*handle, 0)..=(*handle, u64::MAX))
+            .flat_map(|(_, msgs)| msgs.iter().cloned())
+            .collect()
+    }
+}
+
+pub struct WaiterEntry {
+    waiters: Arc<Waiters>,
+    keys: Vec<([u8; 16], u64)>,
+}
+
+impl Drop for WaiterEntry {
+    fn drop(&mut self) {
+        let mut entries = self.waiters.entries();
+        for key in self.keys.iter() {
+            entries.remove(key);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Storage backends of sesman.
+//!
+//! Messages are keyed by 32 bytes which begin with the expiry time of their
+//! session, so that expired messages are always at the front of the keyspace.
+//! Both backends keep the keys in order, which is all the `recycle` task relies on.
+//! Session records are kept in a separate table, keyed by session id.
+//! Metadata of the messages are kept in another table, under the same keys as the messages.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use crossbeam_skiplist::SkipMap;
+use erreur::*;
+
+pub trait Storage: Send + Sync {
+    fn get(&self, key: &[u8; 32]) -> Resultat<Option<Vec<u8>>>;
+
+    /// Insert unless the key exists. Return the value found under the key, if any,
+    /// in which case `val` is not inserted.
+    fn insert_once(&self, key: [u8; 32], val: Vec<u8>) -> Resultat<Option<Vec<u8>>>;
+
+    /// Remove every message, and its metadata, whose key is not greater than `pivot`.
+    /// Return the number of removed messages.
+    fn remove_until(&self, pivot: &[u8; 32]) -> Resultat<usize>;
+
+    /// Remove every message, and its metadata, whose key begins with `prefix`.
+    /// Return the number of removed messages.
+    fn remove_prefix(&self, prefix: &[u8; 16]) -> Resultat<usize>;
+
+    fn insert_meta(&self, key: [u8; 32], meta: Vec<u8>) -> Resultat<()>;
+
+    /// Metadata of the messages whose keys begin with `prefix`, in the order of the keys.
+    fn scan_meta(&self, prefix: &[u8; 16]) -> Resultat<Vec<Vec<u8>>>;
+
+    fn get_session(&self, sid: &str) -> Resultat<Option<Vec<u8>>>;
+
+    fn insert_session(&self, sid: &str, rec: Vec<u8>) -> Resultat<()>;
+
+    fn remove_session(&self, sid: &str) -> Resultat<()>;
+
+    /// All the session records, in no particular order.
+    fn sessions(&self) -> Resultat<Vec<(String, Vec<u8>)>>;
+
+    /// Number of the stored messages.
+    fn len(&self) -> Resultat<usize>;
+
+    /// Make sure that everything inserted so far survives a restart.
+    fn flush(&self) -> Resultat<()>;
+}
+
+/// Keeps everything in memory. Nothing survives a restart.
+#[derive(Default)]
+pub struct MemStore {
+    msgs: SkipMap<[u8; 32], Vec<u8>>,
+    meta: SkipMap<[u8; 32], Vec<u8>>,
+    sessions: SkipMap<String, Vec<u8>>,
+    /// Held by `insert_once`, which `SkipMap` cannot tell inserted from found.
+    inserting: Mutex<()>,
+}
+
+impl Storage for MemStore {
+    fn get(&self, key: &[u8; 32]) -> Resultat<Option<Vec<u8>>> {
+        let val = self.msgs.get(key).map(|entry| entry.value().clone());
+        Ok(val)
+    }
+
+    fn insert_once(&self, key: [u8; 32], val: Vec<u8>) -> Resultat<Option<Vec<u8>>> {
+        let _inserting = self.inserting.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = self.msgs.get(&key) {
+            return Ok(Some(entry.value().clone()));
+        }
+        self.msgs.insert(key, val);
+        Ok(None)
+    }
+
+    fn remove_until(&self, pivot: &[u8; 32]) -> Resultat<usize> {
+        let mut n = 0;
+        while let Some(entry) = self.msgs.front() {
+            if entry.key() > pivot {
+                // recently added item
+                break;
+            }
+            // outdated item
+            if entry.remove() {
+                n += 1;
+            }
+        }
+        while let Some(entry) = self.meta.front() {
+            if entry.key() > pivot {
+                break;
+            }
+            entry.remove();
+        }
+        Ok(n)
+    }
+
+    fn remove_prefix(&self, prefix: &[u8; 16]) -> Resultat<usize> {
+        let mut lo = [0u8; 32];
+        let mut hi = [0xffu8; 32];
+        lo[..16].copy_from_slice(prefix);
+        hi[..16].copy_from_slice(prefix);
+        let mut n = 0;
+        for entry in self.msgs.range(lo..=hi) {
+            if entry.remove() {
+                n += 1;
+            }
+        }
+        for entry in self.meta.range(lo..=hi) {
+            entry.remove();
+        }
+        Ok(n)
+    }
+
+    fn insert_meta(&self, key: [u8; 32], meta: Vec<u8>) -> Resultat<()> {
+        self.meta.insert(key, meta);
+        Ok(())
+    }
+
+    fn scan_meta(&self, prefix: &[u8; 16]) -> Resultat<Vec<Vec<u8>>> {
+        let mut lo = [0u8; 32];
+        let mut hi = [0xffu8; 32];
+        lo[..16].copy_from_slice(prefix);
+        hi[..16].copy_from_slice(prefix);
+        let metas = self
+            .meta
+            .range(lo..=hi)
+            .map(|entry| entry.value().clone())
+            .collect();
+        Ok(metas)
+    }
+
+    fn get_session(&self, sid: &str) -> Resultat<Option<Vec<u8>>> {
+        let rec = self.sessions.get(sid).map(|entry| entry.value().clone());
+        Ok(rec)
+    }
+
+    fn insert_session(&self, sid: &str, rec: Vec<u8>) -> Resultat<()> {
+        self.sessions.insert(sid.to_owned(), rec);
+        Ok(())
+    }
+
+    fn remove_session(&self, sid: &str) -> Resultat<()> {
+        self.sessions.remove(sid);
+        Ok(())
+    }
+
+    fn sessions(&self) -> Resultat<Vec<(String, Vec<u8>)>> {
+        let recs = self
+            .sessions
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        Ok(recs)
+    }
+
+    fn len(&self) -> Resultat<usize> {
+        Ok(self.msgs.len())
+    }
+
+    fn flush(&self) -> Resultat<()> {
+        Ok(())
+    }
+}
+
+/// Keeps everything in an embedded on-disk database,
+/// so that in-flight sessions survive a restart of sesman.
+pub struct DiskStore {
+    db: sled::Db,
+    meta: sled::Tree,
+    sessions: sled::Tree,
+    /// Number of the stored messages. Counting them in sled scans every key.
+    len: AtomicUsize,
+}
+
+impl DiskStore {
+    pub fn open(path: &str) -> Resultat<Self> {
+        let db = sled::open(path).catch("", format!("Try opening database {}", path))?;
+        let meta = db.open_tree("meta").catch_()?;
+        let sessions = db.open_tree("sessions").catch_()?;
+        let len = AtomicUsize::new(db.len());
+        Ok(Self {
+            db,
+            meta,
+            sessions,
+            len,
+        })
+    }
+}
+
+impl Storage for DiskStore {
+    fn get(&self, key: &[u8; 32]) -> Resultat<Option<Vec<u8>>> {
+        let val = self.db.get(key).catch_()?.map(|val| val.to_vec());
+        Ok(val)
+    }
+
+    fn insert_once(&self, key: [u8; 32], val: Vec<u8>) -> Resultat<Option<Vec<u8>>> {
+        let res = self
+            .db
+            .compare_and_swap(key, None as Option<&[u8]>, Some(&val[..]))
+            .catch_()?;
+        match res {
+            Ok(()) => {
+                self.len.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            Err(e) => Ok(e.current.map(|cur| cur.to_vec())),
+        }
+    }
+
+    fn remove_until(&self, pivot: &[u8; 32]) -> Resultat<usize> {
+        let mut n = 0;
+        for entry in self.db.range(..=&pivot[..]) {
+            let (k, _) = entry.catch_()?;
+            if self.db.remove(k).catch_()?.is_some() {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                n += 1;
+            }
+        }
+        for entry in self.meta.range(..=&pivot[..]) {
+            let (k, _) = entry.catch_()?;
+            self.meta.remove(k).catch_()?;
+        }
+        Ok(n)
+    }
+
+    fn remove_prefix(&self, prefix: &[u8; 16]) -> Resultat<usize> {
+        let mut n = 0;
+        for entry in self.db.scan_prefix(prefix) {
+            let (k, _) = entry.catch_()?;
+            if self.db.remove(k).catch_()?.is_some() {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                n += 1;
+            }
+        }
+        for entry in self.meta.scan_prefix(prefix) {
+            let (k, _) = entry.catch_()?;
+            self.meta.remove(k).catch_()?;
+        }
+        Ok(n)
+    }
+
+    fn insert_meta(&self, key: [u8; 32], meta: Vec<u8>) -> Resultat<()> {
+        self.meta.insert(key, meta).catch_()?;
+        Ok(())
+    }
+
+    fn scan_meta(&self, prefix: &[u8; 16]) -> Resultat<Vec<Vec<u8>>> {
+        let mut metas = Vec::new();
+        for entry in self.meta.scan_prefix(prefix) {
+            let (_, meta) = entry.catch_()?;
+            metas.push(meta.to_vec());
+        }
+        Ok(metas)
+    }
+
+    fn get_session(&self, sid: &str) -> Resultat<Option<Vec<u8>>> {
+        let rec = self.sessions.get(sid).catch_()?.map(|rec| rec.to_vec());
+        Ok(rec)
+    }
+
+    fn insert_session(&self, sid: &str, rec: Vec<u8>) -> Resultat<()> {
+        self.sessions.insert(sid, rec).catch_()?;
+        Ok(())
+    }
+
+    fn remove_session(&self, sid: &str) -> Resultat<()> {
+        self.sessions.remove(sid).catch_()?;
+        Ok(())
+    }
+
+    fn sessions(&self) -> Resultat<Vec<(String, Vec<u8>)>> {
+        let mut recs = Vec::new();
+        for entry in self.sessions.iter() {
+            let (sid, rec) = entry.catch_()?;
+            let sid = String::from_utf8(sid.to_vec()).catch_()?;
+            recs.push((sid, rec.to_vec()));
+        }
+        Ok(recs)
+    }
+
+    fn len(&self) -> Resultat<usize> {
+        Ok(self.len.load(Ordering::Relaxed))
+    }
+
+    fn flush(&self) -> Resultat<()> {
+        self.db.flush().catch_()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use erreur::*;
+
+    use super::*;
+
+    fn key(prefix: u8, suffix: u8) -> [u8; 32] {
+        let mut key = [prefix; 32];
+        key[31] = suffix;
+        key
+    }
+
+    fn temp_db() -> PathBuf {
+        std::env::temp_dir().join(format!("svarog_sesman_test_{}", uuid::Uuid::now_v7()))
+    }
+
+    /// What both backends should agree on.
+    fn check_store(store: &dyn Storage) -> Resultat<()> {
+        assert_throw!(store.insert_once(key(1, 0), b"a".to_vec())?.is_none());
+        assert_throw!(store.insert_once(key(1, 0), b"b".to_vec())? == Some(b"a".to_vec()));
+        assert_throw!(store.get(&key(1, 0))? == Some(b"a".to_vec()));
+        assert_throw!(store.get(&key(1, 1))?.is_none());
+        for (prefix, suffix) in [(1, 1), (2, 0), (2, 1), (3, 0)] {
+            store.insert_once(key(prefix, suffix), vec![suffix])?;
+            store.insert_meta(key(prefix, suffix), vec![prefix, suffix])?;
+        }
+        assert_throw!(store.len()? == 5);
+        assert_throw!(store.scan_meta(&[2; 16])? == vec![vec![2, 0], vec![2, 1]]);
+
+        // Recycle: everything up to the pivot, inclusive.
+        assert_throw!(store.remove_until(&key(1, 0xff))? == 2);
+        assert_throw!(store.get(&key(1, 1))?.is_none());
+        assert_throw!(store.scan_meta(&[1; 16])?.is_empty());
+        assert_throw!(store.len()? == 3);
+
+        assert_throw!(store.remove_prefix(&[2; 16])? == 2);
+        assert_throw!(store.scan_meta(&[2; 16])?.is_empty());
+        assert_throw!(store.get(&key(3, 0))? == Some(vec![0]));
+        assert_throw!(store.len()? == 1);
+        // Removed keys can be written again.
+        assert_throw!(store.insert_once(key(2, 0), b"c".to_vec())?.is_none());
+        assert_throw!(store.len()? == 2);
+
+        store.insert_session("s1", b"r1".to_vec())?;
+        store.insert_session("s2", b"r2".to_vec())?;
+        store.remove_session("s2")?;
+        assert_throw!(store.get_session("s1")? == Some(b"r1".to_vec()));
+        assert_throw!(store.get_session("s2")?.is_none());
+        assert_throw!(store.sessions()? == vec![("s1".to_owned(), b"r1".to_vec())]);
+        store.flush()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_mem_store() -> Resultat<()> {
+        check_store(&MemStore::default()).catch_()
+    }
+
+    #[test]
+    fn test_disk_store() -> Resultat<()> {
+        let path = temp_db();
+        let res = check_store(&DiskStore::open(path.to_str().ifnone_()?)?);
+        let _ = std::fs::remove_dir_all(&path);
+        res.catch_()
+    }
+
+    /// Sled releases the lock of a dropped database in the background.
+    fn reopen(path: &str) -> Resultat<DiskStore> {
+        for _ in 0..100 {
+            if let Ok(store) = DiskStore::open(path) {
+                return Ok(store);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        DiskStore::open(path)
+    }
+
+    #[test]
+    fn test_disk_store_reopen() -> Resultat<()> {
+        let path = temp_db();
+        let path_str = path.to_str().ifnone_()?;
+        let res = (|| {
+            {
+                let store = DiskStore::open(path_str)?;
+                store.insert_once(key(1, 0), b"a".to_vec())?;
+                store.insert_meta(key(1, 0), b"m".to_vec())?;
+                store.insert_session("s1", b"r1".to_vec())?;
+                store.flush()?;
+            }
+            let store = reopen(path_str)?;
+            assert_throw!(store.len()? == 1);
+            assert_throw!(store.get(&key(1, 0))? == Some(b"a".to_vec()));
+            assert_throw!(store.insert_once(key(1, 0), b"b".to_vec())? == Some(b"a".to_vec()));
+            assert_throw!(store.scan_meta(&[1; 16])? == vec![b"m".to_vec()]);
+            assert_throw!(store.sessions()? == vec![("s1".to_owned(), b"r1".to_vec())]);
+            Ok(())
+        })();
+        let _ = std::fs::remove_dir_all(&path);
+        res
+    }
+}
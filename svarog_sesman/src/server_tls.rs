@@ -0,0 +1,274 @@
+//! TLS of the gRPC listener, reloadable without restart.
+//!
+//! The certificate, the key and the client CA are read again on SIGHUP,
+//! and when their modification times change if `tls.reload_interval` is positive.
+//! New connections are accepted with the new identity, while established ones
+//! keep going with the old. If a reload fails, the old identity stays in use.
+
+use std::{
+    fs::File,
+    io::BufReader,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use erreur::*;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    signal::unix::{signal, SignalKind},
+    sync::mpsc,
+};
+use tokio_rustls::{
+    rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer},
+        server::WebPkiClientVerifier,
+        RootCertStore, ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, info, warn};
+
+use crate::server_config::TlsConfig;
+
+/// Handshakes taking longer are dropped, so that they do not hold a task forever.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type TlsIncoming = ReceiverStream<std::io::Result<TlsStream<TcpStream>>>;
+
+pub struct TlsReloader {
+    cfg: TlsConfig,
+    current: RwLock<Arc<ServerConfig>>,
+}
+
+fn read_certs(path: &str) -> Resultat<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).catch("", format!("Try reading {}", path))?;
+    let certs: Vec<_> = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<_, _>>()
+        .catch("", format!("Malformed certificates in {}", path))?;
+    assert_throw!(!certs.is_empty(), format!("No certificate in {}", path));
+    Ok(certs)
+}
+
+fn read_key(path: &str) -> Resultat<PrivateKeyDer<'static>> {
+    let file = File::open(path).catch("", format!("Try reading {}", path))?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(file))
+        .catch("", format!("Malformed private key in {}", path))?
+        .ifnone("", format!("No private key in {}", path))?;
+    Ok(key)
+}
+
+fn build(cfg: &TlsConfig) -> Resultat<ServerConfig> {
+    let certs = read_certs(&cfg.cert).catch_()?;
+    let key = read_key(&cfg.key).catch_()?;
+    let builder = ServerConfig::builder();
+    let builder = if cfg.mtls {
+        let mut roots = RootCertStore::empty();
+        for ca in read_certs(&cfg.client_ca).catch_()? {
+            roots
+                .add(ca)
+                .catch("", format!("Invalid CA in {}", &cfg.client_ca))?;
+        }
+        let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+            .build()
+            .catch_()?;
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+    let mut server_cfg = builder
+        .with_single_cert(certs, key)
+        .catch("", format!("Key {} does not fit {}", &cfg.key, &cfg.cert))?;
+    // HTTP/1.1 is for the REST gateway.
+    server_cfg.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(server_cfg)
+}
+
+impl TlsReloader {
+    pub fn new(cfg: TlsConfig) -> Resultat<Arc<Self>> {
+        let server_cfg = build(&cfg).catch_()?;
+        Ok(Arc::new(Self {
+            cfg,
+            current: RwLock::new(Arc::new(server_cfg)),
+        }))
+    }
+
+    /// Read the files again and use them for new connections.
+    pub fn reload(&self) -> Resultat<()> {
+        let server_cfg = build(&self.cfg).catch_()?;
+        *self.current.write().unwrap() = Arc::new(server_cfg);
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+
+    fn mtimes(&self) -> Vec<Option<SystemTime>> {
+        [&self.cfg.cert, &self.cfg.key, &self.cfg.client_ca]
+            .iter()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    /// Reload on SIGHUP, and on changes of the files if `reload_interval` is positive.
+    pub fn watch(self: &Arc<Self>) -> Resultat<()> {
+        let mut hangup = signal(SignalKind::hangup()).catch_()?;
+        let poll = self.cfg.reload_interval > 0;
+        let mut ticker =
+            tokio::time::interval(Duration::from_secs(self.cfg.reload_interval.max(1)));
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut seen = this.mtimes();
+            loop {
+                tokio::select! {
+                    _ = hangup.recv() => {
+                        info!("SIGHUP received, reloading TLS certificates");
+                        seen = this.mtimes();
+                    }
+                    _ = ticker.tick(), if poll => {
+                        let mtimes = this.mtimes();
+                        if mtimes == seen {
+                            continue;
+                        }
+                        info!("TLS files changed, reloading TLS certificates");
+                        seen = mtimes;
+                    }
+                }
+                match this.reload() {
+                    Ok(()) => info!("TLS certificates reloaded"),
+                    Err(e) => error!("Keep the old TLS certificates: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Accept connections on `addr` and hand over those that complete the handshake.
+    pub async fn incoming(self: Arc<Self>, addr: SocketAddr) -> Resultat<TlsIncoming> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .catch("", format!("Try binding to {}", addr))?;
+        let (tx, rx) = mpsc::channel(128);
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                let (tcp, remote) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!("Failed to accept connection: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let _ = tcp.set_nodelay(true);
+                let acceptor = self.acceptor();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                        Ok(Ok(tls)) => {
+                            let _ = tx.send(Ok(tls)).await;
+                        }
+                        Ok(Err(e)) => debug!("TLS handshake with {} failed: {}", remote, e),
+                        Err(_) => debug!("TLS handshake with {} timed out", remote),
+                    }
+                });
+            }
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use erreur::*;
+    use tokio::time::{sleep, timeout};
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+
+    use super::{read_certs, TlsReloader};
+    use crate::server_config::TlsConfig;
+
+    const TESTDATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata");
+
+    /// Install the identity `name` of the test data as the files of `cfg`.
+    fn install(cfg: &TlsConfig, name: &str) -> Resultat<()> {
+        for (ext, path) in [("pem", &cfg.cert), ("key", &cfg.key)] {
+            let src = format!("{}/server_{}.{}", TESTDATA, name, ext);
+            std::fs::copy(&src, path).catch("", format!("Try copying {}", src))?;
+        }
+        Ok(())
+    }
+
+    /// Whether a client trusting only the identity `name` gets through the handshake.
+    async fn trusted_as(tls: &TlsReloader, name: &str) -> Resultat<bool> {
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(&format!("{}/server_{}.pem", TESTDATA, name)).catch_()? {
+            roots.add(cert).catch_()?;
+        }
+        let client_cfg = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let (client_io, server_io) = tokio::io::duplex(1 << 16);
+        let server = tls.acceptor().accept(server_io);
+        let client = TlsConnector::from(Arc::new(client_cfg))
+            .connect(ServerName::try_from("localhost").catch_()?, client_io);
+        let (_, client) = timeout(Duration::from_secs(5), async {
+            tokio::join!(server, client)
+        })
+        .await
+        .catch_()?;
+        Ok(client.is_ok())
+    }
+
+    #[tokio::test]
+    async fn test_reload() -> Resultat<()> {
+        let dir = std::env::temp_dir().join(format!("svarog_tls_{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).catch_()?;
+        let res = async {
+            let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+            let cfg = TlsConfig {
+                cert: path("cert.pem"),
+                key: path("privkey.pem"),
+                client_ca: path("client_ca.pem"),
+                reload_interval: 1,
+                ..Default::default()
+            };
+            install(&cfg, "a")?;
+            let tls = TlsReloader::new(cfg.clone()).catch_()?;
+            assert_throw!(trusted_as(&tls, "a").await? && !trusted_as(&tls, "b").await?);
+
+            install(&cfg, "b")?;
+            tls.reload().catch_()?;
+            assert_throw!(trusted_as(&tls, "b").await? && !trusted_as(&tls, "a").await?);
+
+            // A broken reload keeps the identity in use.
+            std::fs::write(&cfg.cert, "not a certificate").catch_()?;
+            assert_throw!(tls.reload().is_err());
+            assert_throw!(trusted_as(&tls, "b").await?);
+
+            // Changed files are picked up without SIGHUP.
+            tls.watch().catch_()?;
+            sleep(Duration::from_millis(100)).await;
+            install(&cfg, "a")?;
+            let mut reloaded = false;
+            for _ in 0..50 {
+                reloaded = trusted_as(&tls, "a").await?;
+                if reloaded {
+                    break;
+                }
+                sleep(Duration::from_millis(100)).await;
+            }
+            assert_throw!(reloaded, "Changed TLS files were not reloaded");
+            Ok(())
+        }
+        .await;
+        let _ = std::fs::remove_dir_all(&dir);
+        res
+    }
+}
@@ -0,0 +1,288 @@
+//! Resource accounting of sesman, for admission control.
+//!
+//! Usage is kept in memory. After a restart, the sessions found in the storage
+//! are counted again, but the messages in them are not.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use svarog_grpc::{Limits, Usage};
+use tonic::Status;
+
+#[derive(Default)]
+struct Counts {
+    /// Keyed by the session handle, so that expired sessions are at the front.
+    sessions: BTreeMap<[u8; 16], SessionCounts>,
+    msgs: u64,
+    bytes: u64,
+    /// Keyed by the remote address of the connection.
+    waiters: HashMap<String, u64>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct SessionCounts {
+    msgs: u64,
+    bytes: u64,
+}
+
+/// 0 means unlimited.
+fn exceeds(val: u64, limit: u64) -> bool {
+    limit != 0 && val > limit
+}
+
+pub struct Accounting {
+    limits: Limits,
+    counts: Mutex<Counts>,
+}
+
+#[allow(clippy::result_large_err)]
+impl Accounting {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            counts: Mutex::new(Counts::default()),
+        }
+    }
+
+    fn counts(&self) -> MutexGuard<'_, Counts> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn admit_session(&self, handle: &[u8; 16]) -> Result<(), Status> {
+        let mut counts = self.counts();
+        let n = counts.sessions.len() as u64 + 1;
+        if exceeds(n, self.limits.max_sessions) {
+            return Err(Status::resource_exhausted(format!(
+                "Too many sessions, at most {}",
+                self.limits.max_sessions
+            )));
+        }
+        counts.sessions.entry(*handle).or_default();
+        Ok(())
+    }
+
+    /// Count a session found in the storage, regardless of the limits.
+    pub fn restore_session(&self, handle: &[u8; 16]) {
+        self.counts().sessions.entry(*handle).or_default();
+    }
+
+    pub fn admit_batch(&self, n: usize) -> Result<(), Status> {
+        if exceeds(n as u64, self.limits.max_batch) {
+            return Err(Status::resource_exhausted(format!(
+                "Batch of {} messages exceeds the maximum {}",
+                n, self.limits.max_batch
+            )));
+        }
+        Ok(())
+    }
+
+    /// Admit either all or none of the messages, given by session handle and payload size.
+    pub fn admit_msgs(&self, msgs: &[([u8; 16], u64)]) -> Result<(), Status> {
+        let lim = &self.limits;
+        let mut deltas: HashMap<[u8; 16], SessionCounts> = HashMap::new();
+        let mut bytes = 0;
+        for (handle, size) in msgs.iter() {
+            if exceeds(*size, lim.max_payload) {
+                return Err(Status::resource_exhausted(format!(
+                    "Payload of {} bytes exceeds the maximum {}",
+                    size, lim.max_payload
+                )));
+            }
+            let delta = deltas.entry(*handle).or_default();
+            delta.msgs += 1;
+            delta.bytes += size;
+            bytes += size;
+        }
+
+        let mut counts = self.counts();
+        if exceeds(counts.bytes + bytes, lim.memory_budget) {
+            return Err(Status::resource_exhausted(format!(
+                "Memory budget of {} bytes is used up",
+                lim.memory_budget
+            )));
+        }
+        for (handle, delta) in deltas.iter() {
+            let cur = counts.sessions.get(handle).copied().unwrap_or_default();
+            if exceeds(cur.msgs + delta.msgs, lim.max_msgs_per_session) {
+                return Err(Status::resource_exhausted(format!(
+                    "Session has more than {} messages",
+                    lim.max_msgs_per_session
+                )));
+            }
+            if exceeds(cur.bytes + delta.bytes, lim.max_bytes_per_session) {
+                return Err(Status::resource_exhausted(format!(
+                    "Session has more than {} bytes of messages",
+                    lim.max_bytes_per_session
+                )));
+            }
+        }
+        for (handle, delta) in deltas {
+            let cur = counts.sessions.entry(handle).or_default();
+            cur.msgs += delta.msgs;
+            cur.bytes += delta.bytes;
+            counts.msgs += delta.msgs;
+            counts.bytes += delta.bytes;
+        }
+        Ok(())
+    }
+
+    /// Take back what `admit_msgs` counted for the messages not stored after all.
+    pub fn refund_msgs(&self, msgs: &[([u8; 16], u64)]) {
+        let mut counts = self.counts();
+        for (handle, size) in msgs.iter() {
+            // Unless the session is already forgotten.
+            let Some(cur) = counts.sessions.get_mut(handle) else {
+                continue;
+            };
+            cur.msgs -= 1;
+            cur.bytes -= size;
+            counts.msgs -= 1;
+            counts.bytes -= size;
+        }
+    }
+
+    /// The waiter is counted until the guard is dropped.
+    pub fn admit_waiter(self: &Arc<Self>, conn: &str) -> Result<WaiterGuard, Status> {
+        let mut counts = self.counts();
+        let n = counts.waiters.get(conn).copied().unwrap_or(0) + 1;
+        if exceeds(n, self.limits.max_waiters_per_conn) {
+            return Err(Status::resource_exhausted(format!(
+                "Too many waiters on the connection, at most {}",
+                self.limits.max_waiters_per_conn
+            )));
+        }
+        counts.waiters.insert(conn.to_owned(), n);
+        Ok(WaiterGuard {
+            acc: self.clone(),
+            conn: conn.to_owned(),
+        })
+    }
+
+    /// Forget the sessions whose handles are not greater than `pivot`.
+    pub fn release_until(&self, pivot: &[u8; 16]) {
+        let mut counts = self.counts();
+        while let Some(entry) = counts.sessions.first_entry() {
+            if entry.key() > pivot {
+                break;
+            }
+            let cur = entry.remove();
+            counts.msgs -= cur.msgs;
+            counts.bytes -= cur.bytes;
+        }
+    }
+
+    /// Forget the session, e.g. after it is purged.
+    pub fn release(&self, handle: &[u8; 16]) {
+        let mut counts = self.counts();
+        if let Some(cur) = counts.sessions.remove(handle) {
+            counts.msgs -= cur.msgs;
+            counts.bytes -= cur.bytes;
+        }
+    }
+
+    pub fn usage(&self) -> Usage {
+        let counts = self.counts();
+        Usage {
+            sessions: counts.sessions.len() as u64,
+            messages: counts.msgs,
+            bytes: counts.bytes,
+            waiters: counts.waiters.values().sum(),
+            limits: Some(self.limits.clone()),
+        }
+    }
+}
+
+pub struct WaiterGuard {
+    acc: Arc<Accounting>,
+    conn: String,
+}
+
+impl Drop for WaiterGuard {
+    fn drop(&mut self) {
+        let mut counts = self.acc.counts();
+        if let Some(n) = counts.waiters.get_mut(&self.conn) {
+            *n -= 1;
+            if *n == 0 {
+                counts.waiters.remove(&self.conn);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use erreur::*;
+    use svarog_grpc::Limits;
+    use tonic::Code;
+
+    use super::Accounting;
+
+    fn exhausted<T>(res: Result<T, tonic::Status>) -> bool {
+        res.err().map(|status| status.code()) == Some(Code::ResourceExhausted)
+    }
+
+    #[test]
+    fn test_limits() -> Resultat<()> {
+        let acc = Arc::new(Accounting::new(Limits {
+            max_payload: 10,
+            max_batch: 2,
+            max_msgs_per_session: 3,
+            max_bytes_per_session: 20,
+            max_sessions: 2,
+            max_waiters_per_conn: 1,
+            memory_budget: 25,
+        }));
+        let (s1, s2) = ([1u8; 16], [2u8; 16]);
+        acc.admit_session(&s1).catch_()?;
+        acc.admit_session(&s2).catch_()?;
+        assert_throw!(exhausted(acc.admit_session(&[3u8; 16])));
+
+        assert_throw!(acc.admit_batch(2).is_ok());
+        assert_throw!(exhausted(acc.admit_batch(3)));
+        assert_throw!(exhausted(acc.admit_msgs(&[(s1, 11)])));
+        // All or none.
+        assert_throw!(exhausted(acc.admit_msgs(&[(s1, 5); 4])));
+        assert_throw!(acc.usage().messages == 0);
+        acc.admit_msgs(&[(s1, 10), (s1, 10)]).catch_()?;
+        assert_throw!(exhausted(acc.admit_msgs(&[(s1, 1)])));
+        assert_throw!(exhausted(acc.admit_msgs(&[(s2, 6)])));
+        acc.admit_msgs(&[(s2, 5)]).catch_()?;
+
+        let guard = acc.admit_waiter("conn").catch_()?;
+        assert_throw!(exhausted(acc.admit_waiter("conn")));
+        assert_throw!(acc.admit_waiter("other").is_ok());
+        drop(guard);
+        assert_throw!(acc.admit_waiter("conn").is_ok());
+
+        acc.release(&s1);
+        let usage = acc.usage();
+        assert_throw!((usage.sessions, usage.messages, usage.bytes) == (1, 1, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_refund_msgs() -> Resultat<()> {
+        let acc = Accounting::new(Limits {
+            max_msgs_per_session: 2,
+            ..Default::default()
+        });
+        let s1 = [1u8; 16];
+        acc.admit_session(&s1).catch_()?;
+        for _ in 0..5 {
+            acc.admit_msgs(&[(s1, 7), (s1, 8)]).catch_()?;
+            acc.refund_msgs(&[(s1, 7), (s1, 8)]);
+        }
+        let usage = acc.usage();
+        assert_throw!((usage.messages, usage.bytes) == (0, 0));
+        acc.admit_msgs(&[(s1, 7), (s1, 8)]).catch_()?;
+        // Refunds of a forgotten session are ignored.
+        acc.release(&s1);
+        acc.refund_msgs(&[(s1, 7)]);
+        assert_throw!(acc.usage().messages == 0);
+        Ok(())
+    }
+}